@@ -166,6 +166,17 @@ pub fn v3d_list_to_magnitude_smaspr_list(data: &[Vector3d], base: usize) -> (Vec
     (sma_t, sma_vec, spr_vec)
 }
 
+pub fn v3d_list_to_rms_v3d(data: &[Vector3d]) -> Vector3d {
+    if data.is_empty() {
+        return Vector3d::new(0.0, 0.0, 0.0);
+    }
+    let base = data.len() as f64;
+    let rms: Vec<f64> = Vector3d::axis_iter()
+        .map(|axis| (data.iter().map(|vec| vec.get_axis_val(axis).powi(2)).sum::<f64>() / base).sqrt())
+        .collect();
+    Vector3d::new(rms[0], rms[1], rms[2])
+}
+
 pub fn v3d_list_to_scalar_sma_list(data: &[Vector3d], base: usize, reducer: impl Fn(&Vector3d) -> f64 ) -> (Vec<f64>, Vec<f64>) {
     let mut sma_t   =  Vec::new();
     let mut sma_vec =  Vec::new();
@@ -0,0 +1,129 @@
+use serde::Serialize;
+
+use crate::analise_gyro::{gravity_direction_list, integrate_rate, rate_segments, sma_list, yaw_rate_list, TurnDirection, GRAVITY_SMA_BASE, TURN_MIN_RATE};
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+const G: f64 = 9.81;
+
+pub const CANOPY_START_OFFSET: f64 = 5.0;   // sec after the deployment, skip the opening shock
+pub const TURN_MIN_ANGLE     : f64 = 45.0;  // deg
+pub const TURN_SMA_BASE      : usize = 100;
+pub const SWOOP_MIN_ANGLE    : f64 = 90.0;  // deg
+pub const SWOOP_MIN_G        : f64 = 1.5;
+pub const SWOOP_WINDOW       : f64 = 30.0;  // sec before the landing
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CanopyTurn {
+    pub start    : f64,
+    pub end      : f64,
+    pub direction: TurnDirection,
+    /// deg
+    pub angle    : f64,
+    /// deg/s
+    pub peak_rate: f64,
+    /// accel magnitude, g
+    pub peak_g   : f64,
+    /// GPS ground speed, km/h
+    pub max_speed: Option<f64>,
+    /// GPS altitude lost during the turn, m
+    pub altitude_loss: Option<f64>,
+    pub is_swoop : bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CanopyAnalysis {
+    pub start      : f64,
+    pub end        : f64,
+    pub turns      : Vec<CanopyTurn>,
+    pub swoops     : usize,
+    /// index in `turns`
+    pub final_turn : Option<usize>,
+    /// from the end of the final turn to `end`, sec
+    pub final_turn_to_landing: Option<f64>,
+    /// GPS ground speed over the canopy flight, km/h
+    pub max_speed  : Option<f64>,
+}
+
+impl CanopyAnalysis {
+    pub fn get_final_turn(&self) -> Option<&CanopyTurn> {
+        self.final_turn.and_then(|i| self.turns.get(i))
+    }
+}
+
+
+
+/// GPS max speed and altitude loss between `start` and `end`, sec
+fn gps_stats(data: &TelemetryParsedData, start: f64, end: f64) -> (Option<f64>, Option<f64>) {
+    let fixes: Vec<_> = data.gps_data.t.iter()
+        .zip(data.gps_data.v.iter())
+        .filter(|(t, gps)| gps.is_acquired && **t / 1000.0 >= start && **t / 1000.0 <= end)
+        .map(|(_, gps)| gps)
+        .collect();
+    if fixes.is_empty() {
+        return (None, None);
+    }
+    let max_speed = fixes.iter().map(|gps| gps.speed).fold(0.0, f64::max);
+    let altitude_loss = fixes[0].altitude - fixes[fixes.len() - 1].altitude;
+    (Some(max_speed), Some(altitude_loss))
+}
+
+
+
+/// Canopy flight between `deployment` and `landing` (sec from the file start),
+/// without a landing the flight ends with the gyro data
+pub fn analise_canopy(data: &TelemetryParsedData, deployment: f64, landing: Option<f64>) -> Option<CanopyAnalysis> {
+    if data.gyro_data.v.is_empty() {
+        return None;
+    }
+    let t_all: Vec<f64> = data.gyro_data.t.iter().map(|t| t / 1000.0).collect();
+    let start = deployment + CANOPY_START_OFFSET;
+    let end   = landing.unwrap_or(t_all[t_all.len() - 1]);
+    let up_all = gravity_direction_list(&data.acc_data.v, GRAVITY_SMA_BASE);
+
+    let idx: Vec<usize> = (0..t_all.len().min(up_all.len()).min(data.acc_data.v.len()))
+        .filter(|i| t_all[*i] >= start && t_all[*i] <= end)
+        .collect();
+    if idx.len() < 2 {
+        return None;
+    }
+    let (first, last) = (idx[0], idx[idx.len() - 1]);
+    let t        = &t_all[first..=last];
+    let yaw_rate = yaw_rate_list(&data.gyro_data.v[first..=last], &up_all[first..=last]);
+    let heading  = integrate_rate(t, &yaw_rate);
+    let smoothed = sma_list(&yaw_rate, TURN_SMA_BASE);
+    let g_load: Vec<f64> = data.acc_data.v[first..=last].iter().map(|v| v.magnitude() / G).collect();
+
+    let turns: Vec<CanopyTurn> = rate_segments(&heading, &smoothed, TURN_MIN_RATE, TURN_MIN_ANGLE).into_iter().map(|(s, e)| {
+        let angle  = heading[e] - heading[s];
+        let peak_g = g_load[s..=e].iter().cloned().fold(0.0, f64::max);
+        let (max_speed, altitude_loss) = gps_stats(data, t[s], t[e]);
+        CanopyTurn {
+            start    : t[s],
+            end      : t[e],
+            direction: TurnDirection::from_angle(angle),
+            angle    : angle.abs(),
+            peak_rate: smoothed[s..=e].iter().map(|r| r.abs()).fold(0.0, f64::max),
+            peak_g,
+            max_speed,
+            altitude_loss,
+            is_swoop : angle.abs() >= SWOOP_MIN_ANGLE
+                && peak_g >= SWOOP_MIN_G
+                && landing.map(|l| l - t[e] <= SWOOP_WINDOW).unwrap_or(false),
+        }
+    }).collect();
+
+    let final_turn = if landing.is_some() && !turns.is_empty() { Some(turns.len() - 1) } else { None };
+
+    Some(CanopyAnalysis {
+        start,
+        end,
+        swoops: turns.iter().filter(|turn| turn.is_swoop).count(),
+        final_turn_to_landing: final_turn.map(|i| end - turns[i].end),
+        final_turn,
+        max_speed: gps_stats(data, start, end).0,
+        turns,
+    })
+}
@@ -49,49 +49,49 @@ pub fn stft(data: &[f64], window_size: usize, hop_size: usize) -> Vec<Vec<Comple
     output
 }
 
-pub fn stft_result_analise(sma_data: &[f64], sma_base: usize) {
-    let window_size  = 200; 
-    let hop_size     = 200; 
-    let sample_rate  = 200.0; 
-    let top_cnt      = 5;
+pub fn stft_max_magnitude_list(data: &[f64], window_size: usize, hop_size: usize, sample_rate: f64) -> Vec<(f64, f64, f64)> {
     let freq_resolution = sample_rate / window_size as f64;
     let time_resolution = hop_size as f64 / sample_rate;
 
-    let old_result = get_max_vec_data(sma_data);
-
-    let stft_result = stft(sma_data, window_size, hop_size);
-    let mut top_n: Vec<(f64, usize, f64)> = Vec::with_capacity(top_cnt);
+    stft(data, window_size, hop_size)
+        .iter()
+        .enumerate()
+        .map(|(time_idx, spectrum)| {
+            let mut max_magnitude = 0.0;
+            let mut max_frequency = 0.0;
+            for (freq_idx, complex_val) in spectrum.iter().enumerate() {
+                let magnitude = complex_val.norm();
+                if magnitude > max_magnitude {
+                    max_magnitude = magnitude;
+                    max_frequency = freq_idx as f64 * freq_resolution;
+                }
+            }
+            (time_idx as f64 * time_resolution, max_magnitude, max_frequency)
+        })
+        .collect()
+}
 
-    for (time_idx, spectrum) in stft_result.iter().enumerate() {
-        let mut max_magnitude_in_spectrum = 0.0;
-        let mut max_frequency_in_spectrum = 0.0;
+pub fn stft_result_analise(sma_data: &[f64], sma_base: usize) {
+    let window_size  = 200;
+    let hop_size     = 200;
+    let sample_rate  = 200.0;
+    let top_cnt      = 5;
 
-        for (freq_idx, complex_val) in spectrum.iter().enumerate() {
-            let magnitude = complex_val.norm();
-            if magnitude > max_magnitude_in_spectrum {
-                max_magnitude_in_spectrum = magnitude;
-                max_frequency_in_spectrum = freq_idx as f64 * freq_resolution;
-            }
-        }
+    let old_result = get_max_vec_data(sma_data);
 
-        if top_n.len() < top_cnt {
-            let pos = top_n.binary_search_by_key(&time_idx, |&(_, time, _)| time).unwrap_or_else(|pos| pos);
-            top_n.insert(pos, (max_magnitude_in_spectrum, time_idx, max_frequency_in_spectrum));
-        } else if time_idx > top_n[top_cnt-1].1 {
-            top_n[4] = (max_magnitude_in_spectrum, time_idx, max_frequency_in_spectrum);
-        }
-    }
+    // the first top_cnt - 1 windows and the last one, in time order
+    let windows = stft_max_magnitude_list(sma_data, window_size, hop_size, sample_rate);
+    let top_n: Vec<&(f64, f64, f64)> = windows
+        .iter()
+        .enumerate()
+        .filter(|(time_idx, _)| *time_idx < top_cnt - 1 || *time_idx + 1 == windows.len())
+        .map(|(_, window)| window)
+        .collect();
 
     println!("sma_base: {}", sma_base);
     println!("OLD_RESULT: Time {}, MaxAcc {}", old_result.time, old_result.acc);
-    for (i, (magnitude, time_idx, frequency)) in top_n.iter().enumerate() {
-        println!("NEW_RESULT: Top {}: Time: {} sec., Magnitude: {}, Freq.: {} Hz", 
-                i + 1,
-                *time_idx as f64 * time_resolution,
-                magnitude,
-                frequency
-        );
+    for (i, (time, magnitude, frequency)) in top_n.iter().enumerate() {
+        println!("NEW_RESULT: Top {}: Time: {} sec., Magnitude: {}, Freq.: {} Hz", i + 1, time, magnitude, frequency);
     };
-    println!("");
+    println!();
 }
-
@@ -0,0 +1,189 @@
+use serde::Serialize;
+
+use crate::analise_ev_rnd::stft;
+use crate::analise_gyro::sma_list;
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+const G: f64 = 9.81;
+
+pub const EXIT_STFT_WINDOW   : usize = 256;
+pub const EXIT_STFT_HOP      : usize = 64;
+pub const VIB_MIN_FREQ       : f64 = 8.0;    // Hz, below is body motion
+pub const EXIT_SMA_BASE      : usize = 50;
+/// accel magnitude SMA of the first seconds after the exit, g
+pub const EXIT_MAX_G         : f64 = 0.5;
+pub const EXIT_START_G       : f64 = 0.9;
+/// vibration is compared over this before and after a candidate exit
+pub const VIB_COMPARE_WINDOW : f64 = 10.0;   // sec
+/// climb starts where the vibration level falls below this part of the aircraft median
+pub const CLIMB_VIB_RATIO    : f64 = 0.3;
+pub const CLIMB_MIN_GAP      : f64 = 5.0;    // sec of low vibration that ends the climb search
+pub const GPS_MAX_AGE        : f64 = 3.0;    // sec
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitAnalysis {
+    /// sec from the file start, the time zero of the jump
+    pub time            : f64,
+    /// start of the aircraft vibration before the exit
+    pub climb_start     : f64,
+    pub climb_duration  : f64,
+    /// GPS, m
+    pub exit_altitude   : Option<f64>,
+    /// median dominant vibration frequency over the climb, Hz
+    pub vibration_freq  : f64,
+    /// vibration level before / after the exit
+    pub vibration_ratio : f64,
+}
+
+/// Vibration band level and the dominant frequency per STFT window
+pub struct VibrationSpectrum {
+    /// window centers, sec from the file start
+    pub t    : Vec<f64>,
+    pub level: Vec<f64>,
+    pub freq : Vec<f64>,
+}
+
+
+
+pub fn acc_sample_rate(data: &TelemetryParsedData) -> Option<f64> {
+    let t = &data.acc_data.t;
+    if t.len() < 2 || t[t.len() - 1] <= t[0] {
+        return None;
+    }
+    Some((t.len() - 1) as f64 / (t[t.len() - 1] - t[0]) * 1000.0)
+}
+
+/// STFT of the accel magnitude first difference, the band from `VIB_MIN_FREQ` to Nyquist
+pub fn vibration_spectrum(data: &TelemetryParsedData, window_size: usize, hop_size: usize) -> Option<VibrationSpectrum> {
+    let sample_rate = acc_sample_rate(data)?;
+    if data.acc_data.v.len() <= window_size {
+        return None;
+    }
+    let magnitude: Vec<f64> = data.acc_data.v.iter().map(|v| v.magnitude()).collect();
+    let diff: Vec<f64> = magnitude.windows(2).map(|w| w[1] - w[0]).collect();
+
+    let freq_resolution = sample_rate / window_size as f64;
+    let t_start = data.acc_data.t[0] / 1000.0;
+    let window_center = window_size as f64 / sample_rate / 2.0;
+
+    let mut spectrum = VibrationSpectrum { t: Vec::new(), level: Vec::new(), freq: Vec::new() };
+    for (i, window) in stft(&diff, window_size, hop_size).iter().enumerate() {
+        let mut level = 0.0;
+        let mut max_magnitude = 0.0;
+        let mut max_frequency = 0.0;
+        for (freq_idx, complex_val) in window.iter().enumerate().take(window_size / 2 - 1) {
+            let freq = (freq_idx + 1) as f64 * freq_resolution;
+            if freq < VIB_MIN_FREQ { continue }
+            let magnitude = complex_val.norm();
+            level += magnitude * magnitude;
+            if magnitude > max_magnitude {
+                max_magnitude = magnitude;
+                max_frequency = freq;
+            }
+        }
+        spectrum.t.push(t_start + (i * hop_size) as f64 / sample_rate + window_center);
+        spectrum.level.push(level.sqrt());
+        spectrum.freq.push(max_frequency);
+    }
+    Some(spectrum)
+}
+
+fn mean_level_between(spectrum: &VibrationSpectrum, start: f64, end: f64) -> f64 {
+    let (sum, cnt) = spectrum.t.iter()
+        .zip(spectrum.level.iter())
+        .filter(|(t, _)| **t >= start && **t < end)
+        .fold((0.0, 0usize), |(sum, cnt), (_, level)| (sum + level, cnt + 1));
+    if cnt > 0 { sum / cnt as f64 } else { 0.0 }
+}
+
+fn median(mut data: Vec<f64>) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    data.sort_by(f64::total_cmp);
+    data[data.len() / 2]
+}
+
+fn gps_altitude_at(data: &TelemetryParsedData, time: f64) -> Option<f64> {
+    data.gps_data.t.iter()
+        .zip(data.gps_data.v.iter())
+        .filter(|(t, gps)| gps.is_acquired && (**t / 1000.0 - time).abs() <= GPS_MAX_AGE)
+        .min_by(|a, b| (a.0 / 1000.0 - time).abs().total_cmp(&(b.0 / 1000.0 - time).abs()))
+        .map(|(_, gps)| gps.altitude)
+}
+
+
+
+/// Exit as the low-g dip before `before` (sec, e.g. the deployment) with the strongest
+/// drop of the aircraft vibration
+pub fn detect_exit(data: &TelemetryParsedData, before: Option<f64>) -> Option<ExitAnalysis> {
+    let spectrum = vibration_spectrum(data, EXIT_STFT_WINDOW, EXIT_STFT_HOP)?;
+    let t: Vec<f64> = data.acc_data.t.iter().map(|t| t / 1000.0).collect();
+    let magnitude: Vec<f64> = data.acc_data.v.iter().map(|v| v.magnitude() / G).collect();
+    let sma_g = sma_list(&magnitude, EXIT_SMA_BASE);
+    let before = before.unwrap_or(f64::MAX);
+
+    // starts of the low-g dips
+    let mut candidates = Vec::new();
+    let mut in_dip = false;
+    for i in 0..sma_g.len().min(t.len()) {
+        if t[i] >= before { break }
+        if sma_g[i] < EXIT_MAX_G && !in_dip {
+            let mut start = i;
+            while start > 0 && sma_g[start - 1] < EXIT_START_G {
+                start -= 1;
+            }
+            candidates.push(t[start]);
+        }
+        in_dip = sma_g[i] < EXIT_MAX_G;
+    }
+
+    let (time, vibration_ratio) = candidates.into_iter()
+        .map(|c| {
+            let level_before = mean_level_between(&spectrum, c - VIB_COMPARE_WINDOW, c);
+            let level_after  = mean_level_between(&spectrum, c, c + VIB_COMPARE_WINDOW);
+            (c, level_before / level_after.max(f64::EPSILON))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // climb: walk back from the exit while the vibration stays near the aircraft level
+    let aircraft_levels: Vec<f64> = spectrum.t.iter()
+        .zip(spectrum.level.iter())
+        .filter(|(t, _)| **t < time && **t >= time - VIB_COMPARE_WINDOW)
+        .map(|(_, level)| *level)
+        .collect();
+    let threshold = median(aircraft_levels) * CLIMB_VIB_RATIO;
+
+    let mut climb_start = time;
+    let mut last_loud  = time;
+    for (t, level) in spectrum.t.iter().zip(spectrum.level.iter()).rev().filter(|(t, _)| **t < time) {
+        if *level >= threshold {
+            climb_start = *t;
+            last_loud   = *t;
+        } else if last_loud - t >= CLIMB_MIN_GAP {
+            break;
+        }
+    }
+    // loud from the first window, the climb was recorded from the file start
+    let climb_start = if climb_start <= spectrum.t[0] { t[0] } else { climb_start };
+
+    let vibration_freq = median(
+        spectrum.t.iter()
+            .zip(spectrum.freq.iter())
+            .filter(|(t, _)| **t >= climb_start && **t < time)
+            .map(|(_, freq)| *freq)
+            .collect()
+    );
+
+    Some(ExitAnalysis {
+        time,
+        climb_start,
+        climb_duration: time - climb_start,
+        exit_altitude : gps_altitude_at(data, time),
+        vibration_freq,
+        vibration_ratio,
+    })
+}
@@ -0,0 +1,131 @@
+use serde::Serialize;
+
+use crate::analise::{v3d_list_to_magnitude_smaspr_list, v3d_list_to_rms_v3d};
+use crate::analise_gyro::{gravity_direction_list, integrate_rate, rate_segments, sma_list, yaw_rate_list, TurnDirection, GRAVITY_SMA_BASE, TURN_MIN_RATE};
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+/// relative wind builds up after the exit, the first seconds are not a stable body position
+pub const FREEFALL_START_OFFSET: f64 = 5.0;   // sec after the exit
+pub const FREEFALL_END_MARGIN  : f64 = 3.0;   // sec before the deployment
+pub const STABLE_MAX_RATE      : f64 = 30.0;  // deg/s, smoothed rate magnitude
+pub const FREEFALL_SMA_BASE    : usize = 50;
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FreefallTurn {
+    pub start    : f64,
+    pub end      : f64,
+    pub direction: TurnDirection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FreefallStability {
+    pub start         : f64,
+    pub end           : f64,
+    /// deg/s
+    pub rms_x         : f64,
+    pub rms_y         : f64,
+    pub rms_z         : f64,
+    pub rms_total     : f64,
+    /// full 360 deg turns about the relative wind axis
+    pub turns         : Vec<FreefallTurn>,
+    pub rate_threshold: f64,
+    /// longest interval with the smoothed rate below `rate_threshold`
+    pub longest_stable: Option<(f64, f64)>,
+    /// total time below `rate_threshold`, sec
+    pub stable_time   : f64,
+    pub stable_ratio  : f64,
+}
+
+impl FreefallStability {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+    pub fn turns_count(&self, direction: TurnDirection) -> usize {
+        self.turns.iter().filter(|turn| turn.direction == direction).count()
+    }
+}
+
+
+
+/// Every full 360 deg of `heading` inside a same direction run of `smoothed_rate`
+fn full_turns_list(t: &[f64], heading: &[f64], smoothed_rate: &[f64]) -> Vec<FreefallTurn> {
+    let mut turns = Vec::new();
+    for (run_start, run_end) in rate_segments(heading, smoothed_rate, TURN_MIN_RATE, 360.0) {
+        let mut s = run_start;
+        for i in run_start..=run_end {
+            let angle = heading[i] - heading[s];
+            if angle.abs() >= 360.0 {
+                turns.push(FreefallTurn { start: t[s], end: t[i], direction: TurnDirection::from_angle(angle) });
+                s = i;
+            }
+        }
+    }
+    turns
+}
+
+
+
+/// Body stability between `exit` and `deployment` (sec from the file start)
+pub fn analise_freefall(data: &TelemetryParsedData, exit: f64, deployment: f64, rate_threshold: f64) -> Option<FreefallStability> {
+    let start = exit + FREEFALL_START_OFFSET;
+    let end   = deployment - FREEFALL_END_MARGIN;
+    let t_all: Vec<f64> = data.gyro_data.t.iter().map(|t| t / 1000.0).collect();
+    let up_all = gravity_direction_list(&data.acc_data.v, GRAVITY_SMA_BASE);
+
+    let idx: Vec<usize> = (0..t_all.len().min(up_all.len()))
+        .filter(|i| t_all[*i] >= start && t_all[*i] <= end)
+        .collect();
+    if idx.len() <= FREEFALL_SMA_BASE {
+        return None;
+    }
+    let (first, last) = (idx[0], idx[idx.len() - 1]);
+    let t    = &t_all[first..=last];
+    let gyro = &data.gyro_data.v[first..=last];
+
+    let rms = v3d_list_to_rms_v3d(gyro);
+
+    let yaw_rate = yaw_rate_list(gyro, &up_all[first..=last]);
+    let heading  = integrate_rate(t, &yaw_rate);
+    let turns    = full_turns_list(t, &heading, &sma_list(&yaw_rate, FREEFALL_SMA_BASE));
+
+    // smoothed value `i` is over gyro[i..i + base]
+    let (_, rate_sma, _) = v3d_list_to_magnitude_smaspr_list(gyro, FREEFALL_SMA_BASE);
+    let mut stable_time = 0.0;
+    let mut longest_stable: Option<(f64, f64)> = None;
+    let mut stable_start: Option<usize> = None;
+    for i in 0..=rate_sma.len() {
+        let is_stable = i < rate_sma.len() && rate_sma[i] < rate_threshold;
+        if i > 0 && i < rate_sma.len() && is_stable {
+            stable_time += t[i + FREEFALL_SMA_BASE] - t[i + FREEFALL_SMA_BASE - 1];
+        }
+        match (stable_start, is_stable) {
+            (None, true) => stable_start = Some(i),
+            (Some(s), false) => {
+                let interval = (t[s + FREEFALL_SMA_BASE - 1], t[i + FREEFALL_SMA_BASE - 1]);
+                if longest_stable.map(|(a, b)| interval.1 - interval.0 > b - a).unwrap_or(true) {
+                    longest_stable = Some(interval);
+                }
+                stable_start = None;
+            },
+            _ => {}
+        }
+    }
+
+    let duration = t[t.len() - 1] - t[0];
+    Some(FreefallStability {
+        start: t[0],
+        end  : t[t.len() - 1],
+        rms_x: rms.x,
+        rms_y: rms.y,
+        rms_z: rms.z,
+        rms_total: rms.magnitude(),
+        turns,
+        rate_threshold,
+        longest_stable,
+        stable_time,
+        stable_ratio: if duration > 0.0 { stable_time / duration } else { 0.0 },
+    })
+}
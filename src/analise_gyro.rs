@@ -0,0 +1,190 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::analise::v3d_list_to_ts_sma_v3d_list;
+use crate::telemetry_parser_serv::TelemetryParsedData;
+use crate::utils::u_serv::Vector3d;
+
+
+
+pub const SPIN_MIN_RATE    : f64 = 90.0;  // deg/s
+pub const SPIN_MIN_DURATION: f64 = 1.0;   // sec
+pub const GRAVITY_SMA_BASE : usize = 200;
+pub const TURN_MIN_RATE    : f64 = 20.0;  // deg/s, smoothed yaw rate
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TurnDirection {
+    /// counter-clockwise seen from above
+    Left,
+    Right,
+}
+
+impl TurnDirection {
+    /// from a heading change, positive = counter-clockwise
+    pub fn from_angle(angle: f64) -> Self {
+        if angle > 0.0 { TurnDirection::Left } else { TurnDirection::Right }
+    }
+}
+
+impl fmt::Display for TurnDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TurnDirection::Left  => write!(f, "left"),
+            TurnDirection::Right => write!(f, "right"),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct SpinSegment {
+    pub start    : f64,
+    pub end      : f64,
+    pub peak_rate: f64,
+    pub mean_rate: f64,
+    /// integrated rotation about the vertical axis, deg, positive = counter-clockwise
+    pub heading_change: f64,
+}
+
+pub struct GyroAnalysis {
+    /// seconds from the file start
+    pub t             : Vec<f64>,
+    pub rate_magnitude: Vec<f64>,
+    pub x_rate        : Vec<f64>,
+    pub y_rate        : Vec<f64>,
+    pub z_rate        : Vec<f64>,
+    /// angular rate about the gravity direction, deg/s
+    pub yaw_rate      : Vec<f64>,
+    /// integrated yaw rate, deg
+    pub heading       : Vec<f64>,
+    pub spins         : Vec<SpinSegment>,
+}
+
+
+
+pub fn gyro_rate_magnitude(data: &[Vector3d]) -> Vec<f64> {
+    data.iter().map(Vector3d::magnitude).collect()
+}
+
+/// Unit "up" vector per sample from the accel SMA, first values are padded with the first SMA
+pub fn gravity_direction_list(acc_data: &[Vector3d], base: usize) -> Vec<Vector3d> {
+    let default_up = Vector3d::new(0.0, 0.0, 1.0);
+    if acc_data.len() <= base {
+        let mean = acc_data.iter().fold(Vector3d::new(0.0, 0.0, 0.0), |acc, v| acc.v3add(v));
+        let up = if mean.magnitude() > 0.0 { mean.normalized() } else { default_up };
+        return vec![up; acc_data.len()];
+    }
+    let (_, sma_v3d) = v3d_list_to_ts_sma_v3d_list(acc_data, base);
+    let mut up_list: Vec<Vector3d> = Vec::with_capacity(acc_data.len());
+    up_list.resize(base, sma_v3d[0].normalized());
+    up_list.extend(sma_v3d.iter().map(Vector3d::normalized));
+    up_list
+}
+
+/// Projection of the angular rate on the vertical axis
+pub fn yaw_rate_list(gyro_data: &[Vector3d], up_list: &[Vector3d]) -> Vec<f64> {
+    gyro_data.iter().zip(up_list.iter()).map(|(w, up)| w.dot_product(up)).collect()
+}
+
+/// Trapezoidal integral of `rate` over `t` (sec)
+pub fn integrate_rate(t: &[f64], rate: &[f64]) -> Vec<f64> {
+    let mut integral = Vec::with_capacity(rate.len());
+    let mut acc = 0.0;
+    for i in 0..rate.len().min(t.len()) {
+        if i > 0 {
+            acc += (rate[i] + rate[i - 1]) / 2.0 * (t[i] - t[i - 1]);
+        }
+        integral.push(acc);
+    }
+    integral
+}
+
+/// Trailing SMA, the first `base` values average over what is available
+pub fn sma_list(data: &[f64], base: usize) -> Vec<f64> {
+    let base = base.max(1);
+    let mut window_sum = 0.0;
+    data.iter().enumerate().map(|(i, v)| {
+        window_sum += v;
+        if i >= base { window_sum -= data[i - base]; }
+        window_sum / (i + 1).min(base) as f64
+    }).collect()
+}
+
+/// Intervals where the smoothed rate magnitude stays above `min_rate` for at least `min_duration`
+pub fn detect_spins(t: &[f64], rate_magnitude: &[f64], heading: &[f64], min_rate: f64, min_duration: f64, base: usize) -> Vec<SpinSegment> {
+    let smoothed = sma_list(rate_magnitude, base);
+
+    let mut spins = Vec::new();
+    let mut start_i: Option<usize> = None;
+    for i in 0..=smoothed.len() {
+        let is_spinning = i < smoothed.len() && smoothed[i] >= min_rate;
+        match (start_i, is_spinning) {
+            (None, true) => start_i = Some(i),
+            (Some(s), false) => {
+                let e = i - 1;
+                if t[e] - t[s] >= min_duration {
+                    let segment = &rate_magnitude[s..=e];
+                    spins.push(SpinSegment {
+                        start    : t[s],
+                        end      : t[e],
+                        peak_rate: segment.iter().cloned().fold(0.0, f64::max),
+                        mean_rate: segment.iter().sum::<f64>() / segment.len() as f64,
+                        heading_change: heading[e] - heading[s],
+                    });
+                }
+                start_i = None;
+            },
+            _ => {}
+        }
+    }
+    spins
+}
+
+
+/// Index ranges where the smoothed yaw rate stays above `min_rate` in one direction
+/// and `heading` changes by at least `min_angle`
+pub fn rate_segments(heading: &[f64], smoothed: &[f64], min_rate: f64, min_angle: f64) -> Vec<(usize, usize)> {
+    let mut segments = Vec::new();
+    let mut start_i: Option<usize> = None;
+    for i in 0..=smoothed.len() {
+        let sign = if i < smoothed.len() && smoothed[i].abs() >= min_rate { smoothed[i].signum() } else { 0.0 };
+        if let Some(s) = start_i {
+            if sign != smoothed[s].signum() {
+                if (heading[i - 1] - heading[s]).abs() >= min_angle {
+                    segments.push((s, i - 1));
+                }
+                start_i = None;
+            }
+        }
+        if start_i.is_none() && sign != 0.0 {
+            start_i = Some(i);
+        }
+    }
+    segments
+}
+
+
+
+pub fn analise_gyro(data: &TelemetryParsedData, base: usize) -> GyroAnalysis {
+    let gyro = &data.gyro_data.v;
+    let t: Vec<f64> = data.gyro_data.t.iter().map(|t| t / 1000.0).collect();
+
+    let up_list  = gravity_direction_list(&data.acc_data.v, GRAVITY_SMA_BASE);
+    let yaw_rate = yaw_rate_list(gyro, &up_list);
+    let heading  = integrate_rate(&t, &yaw_rate);
+    let rate_magnitude = gyro_rate_magnitude(gyro);
+    let spins = detect_spins(&t, &rate_magnitude, &heading, SPIN_MIN_RATE, SPIN_MIN_DURATION, base);
+
+    GyroAnalysis {
+        x_rate: gyro.iter().map(|v| v.x).collect(),
+        y_rate: gyro.iter().map(|v| v.y).collect(),
+        z_rate: gyro.iter().map(|v| v.z).collect(),
+        t,
+        rate_magnitude,
+        yaw_rate,
+        heading,
+        spins,
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+const G: f64 = 9.81;
+
+pub const LANDING_MIN_PEAK_G    : f64 = 2.0;
+/// deviation from 1 g that counts as the impact
+pub const IMPACT_THRESHOLD_G    : f64 = 0.3;
+pub const STATIC_MAX_DEVIATION_G: f64 = 0.1;
+pub const STATIC_MIN_DURATION   : f64 = 2.0;   // sec
+pub const STATIC_MAX_RATE       : f64 = 20.0;  // deg/s, if the gyro is in sync with the accel
+/// how long before the static 1 g the impact is searched
+pub const STATIC_SEARCH_WINDOW  : f64 = 10.0;  // sec
+/// last GPS fix before the touchdown is accepted within
+pub const GPS_MAX_AGE           : f64 = 3.0;   // sec
+pub const HARD_LANDING_G        : f64 = 4.0;
+pub const HARD_LANDING_SPEED    : f64 = 25.0;  // km/h
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LandingAnalysis {
+    /// touchdown, start of the impact, sec from the file start
+    pub time            : f64,
+    pub peak_time       : f64,
+    /// accel magnitude, g
+    pub peak_g          : f64,
+    pub impact_duration : f64,
+    /// start of the static 1 g
+    pub static_from     : f64,
+    /// GPS ground speed just before the touchdown, km/h
+    pub horizontal_speed: Option<f64>,
+}
+
+impl LandingAnalysis {
+    pub fn is_hard(&self) -> bool {
+        self.peak_g >= HARD_LANDING_G || self.horizontal_speed.map(|s| s >= HARD_LANDING_SPEED).unwrap_or(false)
+    }
+}
+
+
+
+/// First interval from `from` where the magnitude stays within `STATIC_MAX_DEVIATION_G`
+/// of 1 g (and the rotation below `STATIC_MAX_RATE`) for `STATIC_MIN_DURATION`,
+/// returns the start index and the first index after it
+fn find_static_interval(t: &[f64], g_load: &[f64], rate: Option<&[f64]>, from: usize) -> Option<(usize, usize)> {
+    let is_still = |i: usize| rate.map(|r| r[i] <= STATIC_MAX_RATE).unwrap_or(true)
+        && (g_load[i] - 1.0).abs() <= STATIC_MAX_DEVIATION_G;
+
+    let mut static_start: Option<usize> = None;
+    for i in from..g_load.len() {
+        if !is_still(i) {
+            static_start = None;
+            continue;
+        }
+        let s = *static_start.get_or_insert(i);
+        if t[i] - t[s] >= STATIC_MIN_DURATION {
+            let end = (i..g_load.len()).find(|j| !is_still(*j)).unwrap_or(g_load.len());
+            return Some((s, end));
+        }
+    }
+    None
+}
+
+fn horizontal_speed_before(data: &TelemetryParsedData, time: f64) -> Option<f64> {
+    data.gps_data.t.iter()
+        .zip(data.gps_data.v.iter())
+        .rfind(|(t, gps)| gps.is_acquired && **t / 1000.0 <= time && time - **t / 1000.0 <= GPS_MAX_AGE)
+        .map(|(_, gps)| gps.speed)
+}
+
+
+
+/// The last high-G transient before the first near-static 1 g after `after` (sec)
+pub fn detect_landing(data: &TelemetryParsedData, after: f64) -> Option<LandingAnalysis> {
+    let t: Vec<f64> = data.acc_data.t.iter().map(|t| t / 1000.0).collect();
+    let g_load: Vec<f64> = data.acc_data.v.iter().map(|v| v.magnitude() / G).collect();
+    let len = t.len().min(g_load.len());
+    let (t, g_load) = (&t[..len], &g_load[..len]);
+    let rate: Vec<f64> = data.gyro_data.v.iter().map(|v| v.magnitude()).collect();
+    let rate = if rate.len() >= len { Some(&rate[..len]) } else { None };
+
+    let first = t.iter().position(|t| *t > after)?;
+    let mut from = first;
+    while let Some((static_i, static_end)) = find_static_interval(t, g_load, rate, from) {
+        let peak = (first..static_i)
+            .rev()
+            .take_while(|i| t[static_i] - t[*i] <= STATIC_SEARCH_WINDOW)
+            .find(|i| g_load[*i] >= LANDING_MIN_PEAK_G);
+
+        if let Some(i) = peak {
+            let mut impact_start = i;
+            while impact_start > first && (g_load[impact_start - 1] - 1.0).abs() > IMPACT_THRESHOLD_G {
+                impact_start -= 1;
+            }
+            let mut impact_end = i;
+            while impact_end + 1 < static_i && (g_load[impact_end + 1] - 1.0).abs() > IMPACT_THRESHOLD_G {
+                impact_end += 1;
+            }
+            let peak_i = (impact_start..=impact_end)
+                .max_by(|a, b| g_load[*a].total_cmp(&g_load[*b]))
+                .unwrap_or(i);
+
+            return Some(LandingAnalysis {
+                time            : t[impact_start],
+                peak_time       : t[peak_i],
+                peak_g          : g_load[peak_i],
+                impact_duration : t[impact_end] - t[impact_start],
+                static_from     : t[static_i],
+                horizontal_speed: horizontal_speed_before(data, t[impact_start]),
+            });
+        }
+        from = static_end;
+    }
+    None
+}
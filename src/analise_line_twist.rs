@@ -0,0 +1,134 @@
+use serde::Serialize;
+
+use telemetry_parser::tags_impl::Quaternion;
+
+use crate::analise_gyro::{gravity_direction_list, integrate_rate, rate_segments, sma_list, yaw_rate_list, TurnDirection, GRAVITY_SMA_BASE};
+use crate::telemetry_parser_serv::TelemetryParsedData;
+use crate::utils::u_serv::Vector3d;
+
+
+
+pub const LINE_TWIST_WINDOW     : f64 = 30.0;  // sec after the deployment
+pub const TWIST_MIN_RATE        : f64 = 60.0;  // deg/s, smoothed yaw rate
+pub const TWIST_MIN_DURATION    : f64 = 1.0;   // sec
+pub const UNTWIST_CALM_DURATION : f64 = 2.0;   // sec of no rotation that closes the twist
+pub const TWIST_SMA_BASE        : usize = 50;
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TwistSegment {
+    pub start    : f64,
+    pub end      : f64,
+    pub direction: TurnDirection,
+    /// full and partial turns, 1.0 = 360 deg
+    pub rotations: f64,
+    pub peak_rate: f64,
+    pub mean_rate: f64,
+    /// the same turn measured from the camera orientation, if the file has it
+    pub orientation_rotations: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineTwistAnalysis {
+    pub deployment     : f64,
+    pub window_end     : f64,
+    pub segments       : Vec<TwistSegment>,
+    /// complete rotations over all segments
+    pub full_rotations : usize,
+    /// rotation stopped before the end of the window
+    pub untwisted      : bool,
+    pub untwist_time   : Option<f64>,
+}
+
+impl LineTwistAnalysis {
+    pub fn has_line_twists(&self) -> bool {
+        self.full_rotations > 0
+    }
+}
+
+
+
+fn quat_conj(q: &Quaternion<f64>) -> Quaternion<f64> {
+    Quaternion { w: q.w, x: -q.x, y: -q.y, z: -q.z }
+}
+
+/// Signed angle of the rotation `q` about the unit `axis` (swing-twist decomposition), deg
+fn quat_twist_angle(q: &Quaternion<f64>, axis: &Vector3d) -> f64 {
+    let proj = q.x * axis.x + q.y * axis.y + q.z * axis.z;
+    let mut angle = 2.0 * proj.atan2(q.w);
+    if angle >  std::f64::consts::PI { angle -= 2.0 * std::f64::consts::PI; }
+    if angle < -std::f64::consts::PI { angle += 2.0 * std::f64::consts::PI; }
+    angle.to_degrees()
+}
+
+/// Accumulated rotation about `axis` (camera frame) between `start` and `end`, sec
+pub fn orientation_rotation(data: &TelemetryParsedData, start: f64, end: f64, axis: &Vector3d) -> Option<f64> {
+    let quats: Vec<&Quaternion<f64>> = data.orientation_data.t.iter()
+        .zip(data.orientation_data.v.iter())
+        .filter(|(t, _)| **t / 1000.0 >= start && **t / 1000.0 <= end)
+        .map(|(_, q)| q)
+        .collect();
+    if quats.len() < 2 {
+        return None;
+    }
+    let angle = quats.windows(2)
+        .map(|pair| quat_twist_angle(&(quat_conj(pair[0]) * *pair[1]), axis))
+        .fold(0.0, |acc, a| acc + a);
+    Some(angle / 360.0)
+}
+
+
+
+
+/// Sustained yaw rotation in the `LINE_TWIST_WINDOW` after `deployment` (sec from the file start)
+pub fn analise_line_twist(data: &TelemetryParsedData, deployment: f64) -> Option<LineTwistAnalysis> {
+    if data.gyro_data.v.is_empty() {
+        return None;
+    }
+    let window_end = deployment + LINE_TWIST_WINDOW;
+    let t_all: Vec<f64> = data.gyro_data.t.iter().map(|t| t / 1000.0).collect();
+    let up_all = gravity_direction_list(&data.acc_data.v, GRAVITY_SMA_BASE);
+
+    let idx: Vec<usize> = (0..t_all.len().min(up_all.len()))
+        .filter(|i| t_all[*i] >= deployment && t_all[*i] <= window_end)
+        .collect();
+    if idx.len() < 2 {
+        return None;
+    }
+    let (first, last) = (idx[0], idx[idx.len() - 1]);
+    let t        = &t_all[first..=last];
+    let up_list  = &up_all[first..=last];
+    let yaw_rate = yaw_rate_list(&data.gyro_data.v[first..=last], up_list);
+    let heading  = integrate_rate(t, &yaw_rate);
+    let smoothed = sma_list(&yaw_rate, TWIST_SMA_BASE);
+
+    let segments: Vec<TwistSegment> = rate_segments(&heading, &smoothed, TWIST_MIN_RATE, 180.0)
+        .into_iter()
+        .filter(|(s, e)| t[*e] - t[*s] >= TWIST_MIN_DURATION)
+        .map(|(s, e)| {
+            let turn = heading[e] - heading[s];
+            let rates: Vec<f64> = yaw_rate[s..=e].iter().map(|r| r.abs()).collect();
+            TwistSegment {
+                start    : t[s],
+                end      : t[e],
+                direction: TurnDirection::from_angle(turn),
+                rotations: turn.abs() / 360.0,
+                peak_rate: rates.iter().cloned().fold(0.0, f64::max),
+                mean_rate: rates.iter().fold(0.0, |acc, r| acc + r) / rates.len() as f64,
+                orientation_rotations: orientation_rotation(data, t[s], t[e], &up_list[s])
+                    .map(|r| r.abs()),
+            }
+        })
+        .collect();
+
+    let untwist_time = segments.last().map(|seg| seg.end).filter(|end| t[t.len() - 1] - end >= UNTWIST_CALM_DURATION);
+
+    Some(LineTwistAnalysis {
+        deployment,
+        window_end: t[t.len() - 1],
+        full_rotations: segments.iter().map(|seg| seg.rotations.floor() as usize).sum(),
+        untwisted: untwist_time.is_some(),
+        untwist_time,
+        segments,
+    })
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use telemetry_parser::Input as TpInput;
+
+use crate::telemetry_parser_serv::{get_result_metadata_for_file, TelemetryParsedData};
+
+
+
+/// bump when `TelemetryParsedData` or the way it's filled changes
+pub const CACHE_FORMAT_VERSION: u32 = 3;
+pub const CACHE_FILE_EXTENSION: &str = "bin";
+/// head and tail of the file that go into the content hash
+pub const HASH_CHUNK_SIZE     : u64 = 1024 * 1024;
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileKey {
+    pub path        : String,
+    pub size        : u64,
+    pub mtime_ns    : u128,
+    pub content_hash: u64,
+}
+
+/// Every file the parse reads: all the chapters of the recording and the sidecars
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub path   : String,
+    pub files  : Vec<FileKey>,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key : CacheKey,
+    data: TelemetryParsedData,
+}
+
+
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a of the first and the last `HASH_CHUNK_SIZE` bytes, hashing a 4 GB video fully is as slow as parsing it
+fn content_hash(path: &Path, size: u64) -> std::io::Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = Vec::with_capacity(HASH_CHUNK_SIZE as usize);
+    (&mut file).take(HASH_CHUNK_SIZE).read_to_end(&mut buf)?;
+    let mut hash = fnv1a(FNV_OFFSET, &buf);
+    if size > HASH_CHUNK_SIZE {
+        buf.clear();
+        file.seek(SeekFrom::Start(size.saturating_sub(HASH_CHUNK_SIZE).max(HASH_CHUNK_SIZE)))?;
+        file.take(HASH_CHUNK_SIZE).read_to_end(&mut buf)?;
+        hash = fnv1a(hash, &buf);
+    }
+    Ok(hash)
+}
+
+fn get_file_key(path: &str) -> std::io::Result<FileKey> {
+    let path = fs::canonicalize(path)?;
+    let metadata = fs::metadata(&path)?;
+    let mtime_ns = metadata.modified()?.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    Ok(FileKey {
+        path        : path.to_string_lossy().to_string(),
+        size        : metadata.len(),
+        mtime_ns,
+        content_hash: content_hash(&path, metadata.len())?,
+    })
+}
+
+pub fn get_cache_key(src_file: &str, csv_mapping_path: &str) -> std::io::Result<CacheKey> {
+    let path = fs::canonicalize(src_file)?.to_string_lossy().to_string();
+    let mut paths = telemetry_parser::filesystem::gopro_chapter_paths(&path);
+    paths.extend(TpInput::sidecar_paths(&path));
+    if !csv_mapping_path.is_empty() {
+        paths.push(csv_mapping_path.to_string());
+    }
+    Ok(CacheKey {
+        path,
+        files  : paths.iter().map(|path| get_file_key(path)).collect::<std::io::Result<_>>()?,
+        version: format!("{}-{}-{}", env!("CARGO_PKG_VERSION"), telemetry_parser::VERSION, CACHE_FORMAT_VERSION),
+    })
+}
+
+fn get_cache_path(cache_dir: &str, key: &CacheKey) -> PathBuf {
+    Path::new(cache_dir).join(format!("{:016x}.{}", fnv1a(FNV_OFFSET, key.path.as_bytes()), CACHE_FILE_EXTENSION))
+}
+
+fn load_cached(cache_path: &Path, key: &CacheKey) -> Option<TelemetryParsedData> {
+    let bytes = fs::read(cache_path).ok()?;
+    match bincode::deserialize::<CacheEntry>(&bytes) {
+        Ok(entry) if &entry.key == key => Some(entry.data),
+        _ => None,
+    }
+}
+
+fn save_cached(cache_path: &Path, key: CacheKey, data: TelemetryParsedData) -> (TelemetryParsedData, Option<String>) {
+    let entry = CacheEntry { key, data };
+    let err = fs::create_dir_all(cache_path.parent().unwrap_or(Path::new(".")))
+        .map_err(|e| e.to_string())
+        .and_then(|_| bincode::serialize(&entry).map_err(|e| e.to_string()))
+        .and_then(|bytes| fs::write(cache_path, bytes).map_err(|e| e.to_string()))
+        .err();
+    (entry.data, err)
+}
+
+
+
+/// `get_result_metadata_for_file` through the on-disk cache in `cache_dir`,
+/// an empty `cache_dir` turns the cache off
+pub fn get_cached_metadata_for_file(src_file: &str, cache_dir: &str, csv_mapping_path: &str) -> Result<TelemetryParsedData, String> {
+    if cache_dir.is_empty() {
+        return get_result_metadata_for_file(src_file, csv_mapping_path);
+    }
+    let key = match get_cache_key(src_file, csv_mapping_path) {
+        Ok(key) => key,
+        Err(e)  => {
+            println!("CACHE OFF for {}: {}", src_file, e);
+            return get_result_metadata_for_file(src_file, csv_mapping_path);
+        }
+    };
+    let cache_path = get_cache_path(cache_dir, &key);
+
+    if let Some(mut data) = load_cached(&cache_path, &key) {
+        println!("CACHED {}", src_file);
+        data.file_name = src_file.to_string();
+        return Ok(data);
+    }
+
+    let data = get_result_metadata_for_file(src_file, csv_mapping_path)?;
+    let (data, err) = save_cached(&cache_path, key, data);
+    if let Some(e) = err {
+        println!("ERR: failed to cache {} {}", src_file, e);
+    }
+    Ok(data)
+}
@@ -1,5 +1,4 @@
-
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::ConfigValues;
 
@@ -16,11 +15,90 @@ pub struct CliArgs {
     dest_dir: Option<String>,
     #[arg(short)]
     min_accel: Option<f64>,
+    /// JSON or TOML column mapping for CSV logs without a sidecar one
+    #[arg(long)]
+    csv_mapping: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<CliCommand>,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Run the detectors over a folder of videos with `.labels.toml` sidecars and report the timing errors
+    Evaluate {
+        /// folder with labelled videos (srs_dir_path by default)
+        dir: Option<String>,
+        /// sma base used by the detectors
+        #[arg(short, default_value_t = 50)]
+        base: usize,
+        /// max timing error (sec) counted as a hit
+        #[arg(short, default_value_t = ffmin_parser::evaluate::DEF_TOLERANCE)]
+        tolerance: f64,
+    },
+    /// Generate synthetic jumps (`.gcsv` + `.labels.toml`) with known event times
+    Synth {
+        /// output folder (dest_dir_path by default)
+        dir: Option<String>,
+        /// number of jumps
+        #[arg(short, default_value_t = 10)]
+        count: usize,
+        /// first random seed
+        #[arg(short = 'r', default_value_t = 1)]
+        seed: u64,
+    },
+    /// Watch a folder (or a card mount) and write a jump report for every new video into dest_dir_path
+    Watch {
+        /// folder to watch (srs_dir_path by default)
+        dir: Option<String>,
+        /// sma base used by the detectors
+        #[arg(short, default_value_t = 50)]
+        base: usize,
+        /// process what is there and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Find the time offsets between cameras of the same jump by the accel cross-correlation
+    Sync {
+        /// two or more files, the first one is the reference
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<String>,
+        /// resampling rate, Hz
+        #[arg(short = 'r', default_value_t = ffmin_parser::sync::SYNC_SAMPLE_RATE)]
+        sample_rate: f64,
+    },
+    /// Print the raw telemetry tags of a file, to see what a new camera or firmware writes
+    Dump {
+        file: String,
+        /// group name (`GroupId`), `*` for any
+        #[arg(short)]
+        group: Option<String>,
+        /// tag name (`TagId`) or the native tag description (e.g. GoPro 4CC), `*` for any
+        #[arg(short)]
+        tag: Option<String>,
+        /// from sec
+        #[arg(long)]
+        from: Option<f64>,
+        /// to sec
+        #[arg(long)]
+        to: Option<f64>,
+        /// hex of the raw tag data along with the value
+        #[arg(short = 'x', long)]
+        hex: bool,
+        /// JSON lines output
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Explore files in the terminal UI: events, accel chart, live tuning of sma bases and detectors
+    Tui {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+}
 
 
-pub fn get_cli_merged_config(mut config_values: ConfigValues) -> ConfigValues {
+
+pub fn get_cli_merged_config(mut config_values: ConfigValues) -> (ConfigValues, Option<CliCommand>) {
     let cli_args = CliArgs::parse();
 
     if let Some(arg) = &cli_args.only_dir {
@@ -38,7 +116,10 @@ pub fn get_cli_merged_config(mut config_values: ConfigValues) -> ConfigValues {
     if let Some(arg) = cli_args.min_accel {
         config_values.min_accel_trigger = arg;
     }
+    if let Some(arg) = cli_args.csv_mapping {
+        config_values.csv_mapping_path = arg;
+    }
 
-    println!("{:?}", config_values);
-    config_values
+    eprintln!("{:?}", config_values);
+    (config_values, cli_args.command)
 }
@@ -0,0 +1,63 @@
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+use super::{pick_peak_events, Detector, DetectorEvent, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION};
+
+
+
+/// Abrupt exposure change (canopy overhead, body turning from the ground to the horizon)
+pub struct ExposureStepDetector {
+    /// seconds averaged on each side of the step
+    pub window: f64,
+}
+
+impl Default for ExposureStepDetector {
+    fn default() -> Self {
+        Self { window: 1.0 }
+    }
+}
+
+impl Detector for ExposureStepDetector {
+    fn name(&self) -> &'static str {
+        "exposure_step"
+    }
+
+    fn params(&self) -> Vec<(&'static str, f64)> {
+        vec![("window", self.window)]
+    }
+
+    fn set_param(&mut self, name: &str, value: f64) {
+        if name == "window" {
+            self.window = value.max(0.0);
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        0.5
+    }
+
+    fn detect(&self, data: &TelemetryParsedData, _sma_base: usize) -> Vec<DetectorEvent> {
+        let t = &data.lumen_data.t;
+        let v = &data.lumen_data.v;
+        if v.len() < 3 {
+            return Vec::new();
+        }
+
+        let log_v: Vec<f64> = v.iter().map(|x| x.max(1e-6).ln()).collect();
+        let mut step_t = Vec::with_capacity(v.len());
+        let mut step_v = Vec::with_capacity(v.len());
+
+        let (mut lo, mut hi) = (0, 0);
+        for i in 0..t.len() {
+            while t[lo] < t[i] - self.window { lo += 1; }
+            while hi + 1 < t.len() && t[hi + 1] <= t[i] + self.window { hi += 1; }
+            if lo == i || hi <= i { continue; }
+
+            let before = log_v[lo..i].iter().sum::<f64>() / (i - lo) as f64;
+            let after  = log_v[i..=hi].iter().sum::<f64>() / (hi + 1 - i) as f64;
+            step_t.push(t[i]);
+            step_v.push((after - before).abs());
+        }
+
+        pick_peak_events(&step_t, &step_v, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION)
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+use super::Detector;
+
+
+
+/// Events of different detectors closer than this (sec) are treated as the same event
+pub const FUSION_WINDOW: f64 = 2.0;
+
+
+#[derive(Debug, Clone)]
+pub struct Contribution {
+    pub detector: &'static str,
+    pub time    : f64,
+    pub score   : f64,
+    pub weight  : f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FusedEvent {
+    pub time         : f64,
+    pub score        : f64,
+    pub contributions: Vec<Contribution>,
+}
+
+impl FusedEvent {
+    fn from_contributions(contributions: Vec<Contribution>, total_weight: f64) -> Self {
+        let weighted_sum = contributions.iter().map(|c| c.score * c.weight).sum::<f64>();
+        let time = if weighted_sum > 0.0 {
+            contributions.iter().map(|c| c.time * c.score * c.weight).sum::<f64>() / weighted_sum
+        } else {
+            contributions.iter().map(|c| c.time).sum::<f64>() / contributions.len() as f64
+        };
+
+        FusedEvent {
+            time,
+            score: if total_weight > 0.0 { weighted_sum / total_weight } else { 0.0 },
+            contributions,
+        }
+    }
+}
+
+impl fmt::Display for FusedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Time: {:.2} sec., Score: {:.3}", self.time, self.score)?;
+        for c in &self.contributions {
+            write!(f, "\n    {: <15} {:.3} @ {:.2}s", c.detector, c.score, c.time)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+/// Runs every detector and merges their candidates into one list ranked by the fused score.
+/// The score is the weighted sum of contributions over the total weight of all detectors,
+/// so an event confirmed by more detectors ranks higher.
+pub fn fuse_detector_events(
+    detectors: &[Box<dyn Detector>],
+    data     : &TelemetryParsedData,
+    sma_base : usize,
+) -> Vec<FusedEvent> {
+    let total_weight = detectors.iter().map(|d| d.weight()).sum::<f64>();
+
+    let mut candidates: Vec<Contribution> = detectors
+        .iter()
+        .flat_map(|detector| {
+            detector.detect(data, sma_base).into_iter().map(|ev| Contribution {
+                detector: detector.name(),
+                time    : ev.time,
+                score   : ev.score,
+                weight  : detector.weight(),
+            })
+        })
+        .collect();
+    candidates.sort_by(
+        |a, b| (b.score * b.weight).partial_cmp(&(a.score * a.weight)).unwrap_or(std::cmp::Ordering::Equal)
+    );
+
+    let mut clusters: Vec<Vec<Contribution>> = Vec::new();
+    for candidate in candidates {
+        let cluster = clusters.iter_mut().find(|cluster| {
+            (cluster[0].time - candidate.time).abs() <= FUSION_WINDOW
+            && cluster.iter().all(|c| c.detector != candidate.detector)
+        });
+        match cluster {
+            Some(cluster) => cluster.push(candidate),
+            None          => clusters.push(vec![candidate]),
+        }
+    }
+
+    let mut fused: Vec<FusedEvent> = clusters
+        .into_iter()
+        .map(|contributions| FusedEvent::from_contributions(contributions, total_weight))
+        .collect();
+    fused.sort_by(
+        |a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+    );
+    fused
+}
@@ -0,0 +1,49 @@
+use crate::analise::v3d_list_to_magnitude_sma_list;
+use crate::telemetry_parser_serv::{TelemetryParsedData, DEF_TICK};
+
+use super::{pick_peak_events, Detector, DetectorEvent, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION};
+
+
+
+/// Sharp rise of the angular rate magnitude (snatch and spin-up at opening)
+pub struct GyroSpinDetector {
+    /// seconds between the compared SMA values
+    pub rise_time: f64,
+}
+
+impl Default for GyroSpinDetector {
+    fn default() -> Self {
+        Self { rise_time: 0.5 }
+    }
+}
+
+impl Detector for GyroSpinDetector {
+    fn name(&self) -> &'static str {
+        "gyro_spin"
+    }
+
+    fn params(&self) -> Vec<(&'static str, f64)> {
+        vec![("rise_time", self.rise_time)]
+    }
+
+    fn set_param(&mut self, name: &str, value: f64) {
+        if name == "rise_time" {
+            self.rise_time = value.max(0.0);
+        }
+    }
+
+    fn detect(&self, data: &TelemetryParsedData, sma_base: usize) -> Vec<DetectorEvent> {
+        let rise_len = (self.rise_time / DEF_TICK).round().max(1.0) as usize;
+        if data.gyro_data.v.len() <= sma_base + rise_len {
+            return Vec::new();
+        }
+        let (sma_t, sma_v) = v3d_list_to_magnitude_sma_list(&data.gyro_data.v, sma_base);
+
+        let rise_t: Vec<f64> = sma_t[rise_len..].to_vec();
+        let rise_v: Vec<f64> = (rise_len..sma_v.len())
+            .map(|i| (sma_v[i] - sma_v[i - rise_len]).max(0.0))
+            .collect();
+
+        pick_peak_events(&rise_t, &rise_v, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION)
+    }
+}
@@ -0,0 +1,85 @@
+pub mod sma_peak;
+pub mod stft_energy;
+pub mod exposure_step;
+pub mod gyro_spin;
+pub mod wind_drop;
+pub mod fusion;
+
+
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+pub use fusion::{fuse_detector_events, FusedEvent};
+
+
+
+pub const MIN_EVENTS_SEPARATION: f64 = 2.0;
+pub const MAX_EVENTS_PER_DETECTOR: usize = 5;
+
+
+#[derive(Debug, Clone)]
+pub struct DetectorEvent {
+    pub time : f64,
+    pub score: f64,
+}
+
+
+pub trait Detector {
+    fn name(&self) -> &'static str;
+
+    fn weight(&self) -> f64 {
+        1.0
+    }
+
+    /// Tunable parameters as `(name, value)`, edited live in the TUI
+    fn params(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+
+    fn set_param(&mut self, _name: &str, _value: f64) {}
+
+    /// Candidate events, `time` in seconds, `score` normalized to 0..1
+    fn detect(&self, data: &TelemetryParsedData, sma_base: usize) -> Vec<DetectorEvent>;
+}
+
+
+
+/// All the detectors taking part in the fusion, new ones are registered here
+pub fn default_detectors() -> Vec<Box<dyn Detector>> {
+    vec![
+        Box::new(sma_peak::SmaPeakDetector),
+        Box::new(stft_energy::StftEnergyDetector::default()),
+        Box::new(exposure_step::ExposureStepDetector::default()),
+        Box::new(gyro_spin::GyroSpinDetector::default()),
+        Box::new(wind_drop::WindDropDetector::default()),
+    ]
+}
+
+
+
+/// Picks up to `count` highest local maxima at least `min_separation` sec apart,
+/// scores are relative to the highest one
+pub fn pick_peak_events(t: &[f64], v: &[f64], count: usize, min_separation: f64) -> Vec<DetectorEvent> {
+    let mut sorted_idx: Vec<usize> = (0..v.len().min(t.len()))
+        .filter(|&i| v[i].is_finite())
+        .collect();
+    sorted_idx.sort_by(
+        |a, b| v[*b].partial_cmp(&v[*a]).unwrap_or(std::cmp::Ordering::Equal)
+    );
+
+    let mut peaks: Vec<DetectorEvent> = Vec::with_capacity(count);
+    for i in sorted_idx {
+        if peaks.len() >= count { break; }
+        if peaks.iter().any(|ev| (ev.time - t[i]).abs() < min_separation) {
+            continue;
+        }
+        peaks.push(DetectorEvent{ time: t[i], score: v[i] });
+    }
+
+    let max_score = peaks.first().map(|ev| ev.score).unwrap_or(0.0);
+    if max_score > 0.0 {
+        for ev in peaks.iter_mut() {
+            ev.score = (ev.score / max_score).max(0.0);
+        }
+    }
+    peaks
+}
@@ -0,0 +1,29 @@
+use crate::analise::v3d_list_to_magnitude_sma_list;
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+use super::{pick_peak_events, Detector, DetectorEvent, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION};
+
+
+
+const G: f64 = 9.81;
+
+
+/// Opening shock as the highest peaks of the accel magnitude SMA
+#[derive(Default)]
+pub struct SmaPeakDetector;
+
+impl Detector for SmaPeakDetector {
+    fn name(&self) -> &'static str {
+        "sma_peak"
+    }
+
+    fn detect(&self, data: &TelemetryParsedData, sma_base: usize) -> Vec<DetectorEvent> {
+        if data.acc_data.v.len() <= sma_base {
+            return Vec::new();
+        }
+        let (sma_t, sma_v) = v3d_list_to_magnitude_sma_list(&data.acc_data.v, sma_base);
+        let overload: Vec<f64> = sma_v.iter().map(|v| (v - G).max(0.0)).collect();
+
+        pick_peak_events(&sma_t, &overload, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION)
+    }
+}
@@ -0,0 +1,59 @@
+use crate::analise::v3d_list_to_magnitude_sma_list;
+use crate::analise_ev_rnd::stft_max_magnitude_list;
+use crate::telemetry_parser_serv::{TelemetryParsedData, DEF_TICK};
+
+use super::{pick_peak_events, Detector, DetectorEvent, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION};
+
+
+
+/// Windows with the highest spectral energy of the accel magnitude SMA
+pub struct StftEnergyDetector {
+    pub window_size: usize,
+    pub hop_size   : usize,
+}
+
+impl Default for StftEnergyDetector {
+    fn default() -> Self {
+        Self {
+            window_size: 200,
+            hop_size   : 100,
+        }
+    }
+}
+
+impl Detector for StftEnergyDetector {
+    fn name(&self) -> &'static str {
+        "stft_energy"
+    }
+
+    fn params(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("window_size", self.window_size as f64),
+            ("hop_size"   , self.hop_size as f64),
+        ]
+    }
+
+    fn set_param(&mut self, name: &str, value: f64) {
+        let value = value.round().max(1.0) as usize;
+        match name {
+            "window_size" => self.window_size = value,
+            "hop_size"    => self.hop_size    = value,
+            _ => {}
+        }
+    }
+
+    fn detect(&self, data: &TelemetryParsedData, sma_base: usize) -> Vec<DetectorEvent> {
+        if data.acc_data.v.len() <= sma_base + self.window_size {
+            return Vec::new();
+        }
+        let (sma_t, sma_v) = v3d_list_to_magnitude_sma_list(&data.acc_data.v, sma_base);
+        let t_start = sma_t.first().copied().unwrap_or(0.0);
+        let window_center = self.window_size as f64 * DEF_TICK / 2.0;
+
+        let stft_list = stft_max_magnitude_list(&sma_v, self.window_size, self.hop_size, 1.0 / DEF_TICK);
+        let t: Vec<f64> = stft_list.iter().map(|(t, _, _)| t_start + t + window_center).collect();
+        let v: Vec<f64> = stft_list.iter().map(|(_, magnitude, _)| *magnitude).collect();
+
+        pick_peak_events(&t, &v, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION)
+    }
+}
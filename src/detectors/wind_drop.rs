@@ -0,0 +1,76 @@
+use crate::telemetry_parser_serv::{TelemetryParsedData, TsScalarArr};
+
+use super::{pick_peak_events, Detector, DetectorEvent, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION};
+
+
+
+/// Collapse of the relative wind noise at opening (GoPro WNDM, AALP as a fallback)
+pub struct WindDropDetector {
+    /// seconds averaged on each side of the drop
+    pub window: f64,
+}
+
+impl Default for WindDropDetector {
+    fn default() -> Self {
+        Self { window: 2.0 }
+    }
+}
+
+impl WindDropDetector {
+    fn drop_series(&self, data: &TsScalarArr) -> (Vec<f64>, Vec<f64>) {
+        let (t, v) = (&data.t, &data.v);
+        let mut drop_t = Vec::with_capacity(v.len());
+        let mut drop_v = Vec::with_capacity(v.len());
+
+        let mean = |slice: &[f64]| {
+            let finite: Vec<f64> = slice.iter().copied().filter(|x| x.is_finite()).collect();
+            if finite.is_empty() { None } else { Some(finite.iter().sum::<f64>() / finite.len() as f64) }
+        };
+
+        let (mut lo, mut hi) = (0, 0);
+        for i in 0..t.len() {
+            while t[lo] < t[i] - self.window { lo += 1; }
+            while hi + 1 < t.len() && t[hi + 1] <= t[i] + self.window { hi += 1; }
+            if lo == i || hi <= i { continue; }
+
+            if let (Some(before), Some(after)) = (mean(&v[lo..i]), mean(&v[i..=hi])) {
+                drop_t.push(t[i]);
+                drop_v.push((before - after).max(0.0));
+            }
+        }
+        (drop_t, drop_v)
+    }
+}
+
+impl Detector for WindDropDetector {
+    fn name(&self) -> &'static str {
+        "wind_drop"
+    }
+
+    fn params(&self) -> Vec<(&'static str, f64)> {
+        vec![("window", self.window)]
+    }
+
+    fn set_param(&mut self, name: &str, value: f64) {
+        if name == "window" {
+            self.window = value.max(0.0);
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        0.5
+    }
+
+    fn detect(&self, data: &TelemetryParsedData, _sma_base: usize) -> Vec<DetectorEvent> {
+        let series = if data.wind_data.v.len() > 2 {
+            &data.wind_data
+        } else if data.audio_level_data.v.len() > 2 {
+            &data.audio_level_data
+        } else {
+            return Vec::new();
+        };
+
+        let (drop_t, drop_v) = self.drop_series(series);
+        pick_peak_events(&drop_t, &drop_v, MAX_EVENTS_PER_DETECTOR, MIN_EVENTS_SEPARATION)
+    }
+}
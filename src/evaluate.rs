@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analise_canopy::CANOPY_START_OFFSET;
+use crate::analise_exit::detect_exit;
+use crate::analise_landing::detect_landing;
+use crate::detectors::{default_detectors, fuse_detector_events, FusedEvent};
+use crate::telemetry_parser_serv::{get_result_metadata_for_file, TelemetryParsedData};
+
+
+
+pub const LABELS_FILE_POSTFIX: &str = ".labels.toml";
+pub const EVAL_FILE_EXTENSIONS: [&str; 2] = ["mp4", "gcsv"];
+
+pub const DEF_TOLERANCE       : f64 = 2.0;
+pub const FALSE_ALARM_MIN_SCORE: f64 = 0.5;
+
+
+/// Ground truth sidecar, `<video stem>.labels.toml`, times in seconds from the file start:
+/// ```toml
+/// exit       = 12.4
+/// deployment = 58.9
+/// landing    = 241.0
+/// ```
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct JumpLabels {
+    pub exit      : Option<f64>,
+    pub deployment: Option<f64>,
+    pub landing   : Option<f64>,
+}
+
+impl JumpLabels {
+    pub fn as_list(&self) -> Vec<(&'static str, f64)> {
+        [("exit", self.exit), ("deployment", self.deployment), ("landing", self.landing)]
+            .into_iter()
+            .filter_map(|(kind, time)| time.map(|t| (kind, t)))
+            .collect()
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEvaluation {
+    pub kind     : String,
+    pub true_time: f64,
+    pub predicted: Option<f64>,
+    pub error    : Option<f64>,
+    pub hit      : bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEvaluation {
+    pub file_name   : String,
+    pub events      : Vec<EventEvaluation>,
+    pub false_alarms: usize,
+    pub parse_error : Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct AggregateStats {
+    pub labelled       : usize,
+    pub hits           : usize,
+    pub misses         : usize,
+    pub mean_error     : f64,
+    pub mean_abs_error : f64,
+    pub median_abs_error: f64,
+    pub max_abs_error  : f64,
+    pub rmse           : f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationReport {
+    pub created_unix: u64,
+    pub dir         : String,
+    pub sma_base    : usize,
+    pub tolerance   : f64,
+    pub files       : Vec<FileEvaluation>,
+    pub false_alarms: usize,
+    pub aggregate   : BTreeMap<String, AggregateStats>,
+}
+
+
+
+pub fn get_labels_path(video_path: &Path) -> PathBuf {
+    let stem = video_path.file_stem().unwrap_or_default().to_string_lossy();
+    video_path.with_file_name(format!("{}{}", stem, LABELS_FILE_POSTFIX))
+}
+
+pub fn load_labels(video_path: &Path) -> Option<JumpLabels> {
+    let labels_str = fs::read_to_string(get_labels_path(video_path)).ok()?;
+    match toml::from_str::<JumpLabels>(&labels_str) {
+        Ok(labels) => Some(labels),
+        Err(e) => {
+            println!("INVALID LABELS for {:?}: {}", video_path, e);
+            None
+        }
+    }
+}
+
+pub fn get_labelled_files(dir: &str) -> Vec<(PathBuf, JumpLabels)> {
+    let mut files: Vec<(PathBuf, JumpLabels)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str().map(|s| EVAL_FILE_EXTENSIONS.contains(&s.to_lowercase().as_str())))
+                    .unwrap_or(false)
+            })
+            .filter_map(|path| load_labels(&path).map(|labels| (path, labels)))
+            .collect(),
+        Err(e) => {
+            println!("Failed to read directory {}: {}", dir, e);
+            Vec::new()
+        }
+    };
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+
+
+/// Predicted time of every event kind the detectors can currently find
+pub fn predict_events(data: &TelemetryParsedData, sma_base: usize) -> (BTreeMap<&'static str, f64>, Vec<FusedEvent>) {
+    let fused_events = fuse_detector_events(&default_detectors(), data, sma_base);
+    let mut predicted = BTreeMap::new();
+    if let Some(exit) = detect_exit(data, fused_events.first().map(|ev| ev.time)) {
+        predicted.insert("exit", exit.time);
+    }
+    if let Some(top) = fused_events.first() {
+        predicted.insert("deployment", top.time);
+        if let Some(landing) = detect_landing(data, top.time + CANOPY_START_OFFSET) {
+            predicted.insert("landing", landing.time);
+        }
+    }
+    (predicted, fused_events)
+}
+
+fn evaluate_file(path: &Path, labels: &JumpLabels, sma_base: usize, tolerance: f64) -> FileEvaluation {
+    let file_name = path.to_string_lossy().to_string();
+    // labelled files are videos, no CSV mapping
+    let data = match get_result_metadata_for_file(&file_name, "") {
+        Ok(data) => data,
+        Err(e)   => {
+            return FileEvaluation { file_name, events: Vec::new(), false_alarms: 0, parse_error: Some(e) };
+        }
+    };
+    let (predicted, fused_events) = predict_events(&data, sma_base);
+
+    let events: Vec<EventEvaluation> = labels.as_list().into_iter().map(|(kind, true_time)| {
+        let predicted = predicted.get(kind).copied();
+        let error     = predicted.map(|t| t - true_time);
+        EventEvaluation {
+            kind: kind.to_string(),
+            true_time,
+            predicted,
+            error,
+            hit : error.map(|e| e.abs() <= tolerance).unwrap_or(false),
+        }
+    }).collect();
+
+    let true_times: Vec<f64> = labels.as_list().iter().map(|(_, t)| *t).collect();
+    let false_alarms = fused_events
+        .iter()
+        .filter(|ev| ev.score >= FALSE_ALARM_MIN_SCORE)
+        .filter(|ev| true_times.iter().all(|t| (ev.time - t).abs() > tolerance))
+        .count();
+
+    FileEvaluation { file_name, events, false_alarms, parse_error: None }
+}
+
+fn aggregate_stats(files: &[FileEvaluation]) -> BTreeMap<String, AggregateStats> {
+    let mut by_kind: BTreeMap<String, Vec<&EventEvaluation>> = BTreeMap::new();
+    for ev in files.iter().flat_map(|f| f.events.iter()) {
+        by_kind.entry(ev.kind.clone()).or_default().push(ev);
+    }
+
+    by_kind.into_iter().map(|(kind, events)| {
+        let errors: Vec<f64> = events.iter().filter(|ev| ev.hit).filter_map(|ev| ev.error).collect();
+        let mut abs_errors: Vec<f64> = errors.iter().map(|e| e.abs()).collect();
+        abs_errors.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let n = errors.len().max(1) as f64;
+        let mean = |vals: &mut dyn Iterator<Item = f64>| vals.fold(0.0, |acc, x| acc + x) / n;
+        let hits = events.iter().filter(|ev| ev.hit).count();
+        let stats = AggregateStats {
+            labelled        : events.len(),
+            hits,
+            misses          : events.len() - hits,
+            mean_error      : mean(&mut errors.iter().copied()),
+            mean_abs_error  : mean(&mut abs_errors.iter().copied()),
+            median_abs_error: abs_errors.get(abs_errors.len() / 2).copied().unwrap_or(0.0),
+            max_abs_error   : abs_errors.last().copied().unwrap_or(0.0),
+            rmse            : mean(&mut errors.iter().map(|e| e.powi(2))).sqrt(),
+        };
+        (kind, stats)
+    }).collect()
+}
+
+pub fn evaluate_dir(dir: &str, sma_base: usize, tolerance: f64) -> EvaluationReport {
+    let files: Vec<FileEvaluation> = get_labelled_files(dir)
+        .iter()
+        .map(|(path, labels)| evaluate_file(path, labels, sma_base, tolerance))
+        .collect();
+
+    EvaluationReport {
+        created_unix: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        dir         : dir.to_string(),
+        sma_base,
+        tolerance,
+        false_alarms: files.iter().map(|f| f.false_alarms).sum(),
+        aggregate   : aggregate_stats(&files),
+        files,
+    }
+}
+
+
+
+pub fn print_evaluation_report(report: &EvaluationReport) {
+    println!("\nEVALUATION {} (sma_base {}, tolerance {}s)", report.dir, report.sma_base, report.tolerance);
+    for file in &report.files {
+        println!("{}", file.file_name);
+        if let Some(e) = &file.parse_error {
+            println!("    ERR: {}", e);
+            continue;
+        }
+        for ev in &file.events {
+            match (ev.predicted, ev.error) {
+                (Some(predicted), Some(error)) => println!(
+                    "    {: <11} true {:>8.2}s  predicted {:>8.2}s  error {:>+7.2}s  {}",
+                    ev.kind, ev.true_time, predicted, error, if ev.hit { "HIT" } else { "MISS" }
+                ),
+                _ => println!("    {: <11} true {:>8.2}s  NOT DETECTED", ev.kind, ev.true_time),
+            }
+        }
+        println!("    false alarms: {}", file.false_alarms);
+    }
+
+    println!("\nTOTAL files: {}, false alarms: {}", report.files.len(), report.false_alarms);
+    for (kind, stats) in &report.aggregate {
+        println!(
+            "{: <11} hits {}/{}  mean err {:+.2}s  mean |err| {:.2}s  median |err| {:.2}s  max |err| {:.2}s  rmse {:.2}s",
+            kind, stats.hits, stats.labelled, stats.mean_error, stats.mean_abs_error,
+            stats.median_abs_error, stats.max_abs_error, stats.rmse
+        );
+    }
+}
+
+pub fn save_evaluation_report(report: &EvaluationReport, dest_dir_path: &str) -> std::io::Result<PathBuf> {
+    let mut dest_dir = PathBuf::from(dest_dir_path);
+    if !dest_dir.exists() {
+        dest_dir = PathBuf::from(".");
+    }
+    let report_path = dest_dir.join(format!("evaluate_{}.json", report.created_unix));
+    let report_json = serde_json::to_string_pretty(report)
+        .map_err(std::io::Error::other)?;
+    fs::write(&report_path, report_json)?;
+    Ok(report_path)
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::analise_canopy::{analise_canopy, CanopyAnalysis, CANOPY_START_OFFSET};
+use crate::analise_exit::{detect_exit, ExitAnalysis};
+use crate::analise_freefall::{analise_freefall, FreefallStability, STABLE_MAX_RATE};
+use crate::analise_gyro::TurnDirection;
+use crate::analise_landing::{detect_landing, LandingAnalysis};
+use crate::analise_line_twist::{analise_line_twist, LineTwistAnalysis};
+use crate::detectors::{default_detectors, fuse_detector_events, Detector};
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+pub const FLAG_LINE_TWISTS: &str = "line twists";
+pub const FLAG_SWOOP      : &str = "swoop";
+pub const FLAG_HARD_LANDING: &str = "hard landing";
+
+
+/// Per jump results, times in seconds from the file start
+#[derive(Debug, Clone, Serialize)]
+pub struct JumpReport {
+    pub file_name       : String,
+    pub camera_model    : String,
+    pub camera_serial   : Option<String>,
+    pub sma_base        : usize,
+    pub exit            : Option<ExitAnalysis>,
+    pub freefall        : Option<FreefallStability>,
+    pub deployment      : Option<f64>,
+    pub deployment_score: Option<f64>,
+    pub line_twists     : Option<LineTwistAnalysis>,
+    pub canopy          : Option<CanopyAnalysis>,
+    pub landing         : Option<LandingAnalysis>,
+    pub flags           : Vec<String>,
+}
+
+
+
+pub fn build_jump_report(data: &TelemetryParsedData, sma_base: usize) -> JumpReport {
+    build_jump_report_with(data, sma_base, &default_detectors(), STABLE_MAX_RATE)
+}
+
+/// `build_jump_report` with tuned detectors and freefall stability threshold (deg/s)
+pub fn build_jump_report_with(
+    data           : &TelemetryParsedData,
+    sma_base       : usize,
+    detectors      : &[Box<dyn Detector>],
+    stable_max_rate: f64,
+) -> JumpReport {
+    let fused_events = fuse_detector_events(detectors, data, sma_base);
+    let top = fused_events.first();
+    let deployment = top.map(|ev| ev.time);
+
+    let exit        = detect_exit(data, deployment);
+    let freefall    = match (&exit, deployment) {
+        (Some(exit), Some(deployment)) => analise_freefall(data, exit.time, deployment, stable_max_rate),
+        _                              => None,
+    };
+    let line_twists = deployment.and_then(|t| analise_line_twist(data, t));
+    let landing     = deployment.and_then(|t| detect_landing(data, t + CANOPY_START_OFFSET));
+    let canopy      = deployment.and_then(|t| analise_canopy(data, t, landing.as_ref().map(|l| l.time)));
+
+    let mut flags = Vec::new();
+    if line_twists.as_ref().map(LineTwistAnalysis::has_line_twists).unwrap_or(false) {
+        flags.push(FLAG_LINE_TWISTS.to_string());
+    }
+    if canopy.as_ref().map(|c| c.swoops > 0).unwrap_or(false) {
+        flags.push(FLAG_SWOOP.to_string());
+    }
+    if landing.as_ref().map(LandingAnalysis::is_hard).unwrap_or(false) {
+        flags.push(FLAG_HARD_LANDING.to_string());
+    }
+
+    JumpReport {
+        file_name       : data.file_name.clone(),
+        camera_model    : data.cam_info.model.clone(),
+        camera_serial   : data.cam_info.serial.clone(),
+        sma_base,
+        exit,
+        freefall,
+        deployment,
+        deployment_score: top.map(|ev| ev.score),
+        line_twists,
+        canopy,
+        landing,
+        flags,
+    }
+}
+
+
+
+impl JumpReport {
+    /// `time` (sec from the file start) relative to the exit
+    pub fn from_exit(&self, time: f64) -> Option<f64> {
+        self.exit.as_ref().map(|exit| time - exit.time)
+    }
+
+    fn format_from_exit(&self, time: f64) -> String {
+        self.from_exit(time).map(|t| format!("{:+.2}s", t)).unwrap_or("-".to_string())
+    }
+}
+
+
+impl fmt::Display for JumpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "JUMP {} [{} {}], sma_base: {}",
+            self.file_name,
+            self.camera_model,
+            self.camera_serial.as_deref().unwrap_or("-"),
+            self.sma_base,
+        )?;
+        match self.exit {
+            Some(ref exit) => writeln!(f, "  exit: {:.2}s, climb {:.0}s from {:.2}s, aircraft vibration {:.1} Hz{}",
+                exit.time,
+                exit.climb_duration,
+                exit.climb_start,
+                exit.vibration_freq,
+                exit.exit_altitude.map(|a| format!(", altitude {:.0} m", a)).unwrap_or_default(),
+            )?,
+            None           => writeln!(f, "  exit: not found")?,
+        }
+        if let Some(ref ff) = self.freefall {
+            writeln!(f, "  freefall {:.2}s - {:.2}s: rms x {:.0} y {:.0} z {:.0} deg/s, 360s: {} left {} right",
+                ff.start,
+                ff.end,
+                ff.rms_x,
+                ff.rms_y,
+                ff.rms_z,
+                ff.turns_count(TurnDirection::Left),
+                ff.turns_count(TurnDirection::Right),
+            )?;
+            writeln!(f, "  stable < {:.0} deg/s: {:.1}s ({:.0}%){}",
+                ff.rate_threshold,
+                ff.stable_time,
+                ff.stable_ratio * 100.0,
+                ff.longest_stable.map(|(a, b)| format!(", longest {:.1}s from {:.2}s", b - a, a)).unwrap_or_default(),
+            )?;
+        }
+        match (self.deployment, self.deployment_score) {
+            (Some(t), Some(score)) => writeln!(f, "  deployment: {:.2}s (exit {}) (score {:.2})", t, self.format_from_exit(t), score)?,
+            _                      => writeln!(f, "  deployment: not found")?,
+        }
+        if let Some(ref twists) = self.line_twists {
+            for seg in &twists.segments {
+                writeln!(f, "  twist {:.2}s - {:.2}s: {:.1} turns {}, peak {:.0} deg/s, mean {:.0} deg/s{}",
+                    seg.start,
+                    seg.end,
+                    seg.rotations,
+                    seg.direction,
+                    seg.peak_rate,
+                    seg.mean_rate,
+                    seg.orientation_rotations.map(|r| format!(" (orientation {:.1} turns)", r)).unwrap_or_default(),
+                )?;
+            }
+            if twists.has_line_twists() {
+                match twists.untwist_time {
+                    Some(t) => writeln!(f, "  untwisted at {:.2}s", t)?,
+                    None    => writeln!(f, "  still twisted at {:.2}s", twists.window_end)?,
+                }
+            }
+        }
+        if let Some(ref canopy) = self.canopy {
+            writeln!(f, "  canopy {:.2}s - {:.2}s: {} turns, {} swoops{}",
+                canopy.start,
+                canopy.end,
+                canopy.turns.len(),
+                canopy.swoops,
+                canopy.max_speed.map(|s| format!(", max speed {:.0} km/h", s)).unwrap_or_default(),
+            )?;
+            for turn in &canopy.turns {
+                writeln!(f, "    turn {:.2}s - {:.2}s: {:.0} deg {}, peak {:.0} deg/s, {:.2} g{}{}",
+                    turn.start,
+                    turn.end,
+                    turn.angle,
+                    turn.direction,
+                    turn.peak_rate,
+                    turn.peak_g,
+                    turn.max_speed.map(|s| format!(", {:.0} km/h", s)).unwrap_or_default(),
+                    if turn.is_swoop { " SWOOP" } else { "" },
+                )?;
+            }
+            if let (Some(turn), Some(dt)) = (canopy.get_final_turn(), canopy.final_turn_to_landing) {
+                writeln!(f, "  final turn {:.0} deg {} at {:.2}s, {:.2}s before landing", turn.angle, turn.direction, turn.start, dt)?;
+            }
+        }
+        match self.landing {
+            Some(ref landing) => writeln!(f, "  landing: {:.2}s (exit {}), peak {:.2} g, impact {:.2}s{} [{}]",
+                landing.time,
+                self.format_from_exit(landing.time),
+                landing.peak_g,
+                landing.impact_duration,
+                landing.horizontal_speed.map(|s| format!(", {:.0} km/h", s)).unwrap_or_default(),
+                self.camera_serial.as_deref().unwrap_or("-"),
+            )?,
+            None              => writeln!(f, "  landing: not found")?,
+        }
+        write!(f, "  flags: [{}]", self.flags.join(", "))
+    }
+}
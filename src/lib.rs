@@ -0,0 +1,67 @@
+//! Skydive jump analysis from action camera telemetry:
+//! parse a file (`get_result_metadata_for_file`), run the analysers (`build_jump_report`), get a `JumpReport`.
+//! Library code takes everything it needs as arguments, no config globals and no stdin.
+
+pub mod utils {
+    pub mod error;
+    pub mod u_serv;
+    pub use u_serv::abs_max;
+}
+
+pub mod analise;
+pub mod analise_canopy;
+pub mod analise_ev_rnd;
+pub mod analise_exit;
+pub mod analise_freefall;
+pub mod analise_gyro;
+pub mod analise_landing;
+pub mod analise_line_twist;
+pub mod analise_log_v;
+pub mod cache;
+pub mod detectors;
+pub mod evaluate;
+pub mod jump_report;
+pub mod session;
+pub mod sync;
+
+pub mod telemetry_parser_serv;
+pub mod file_sys_serv;
+
+
+
+use std::path::PathBuf;
+
+pub use cache::get_cached_metadata_for_file;
+pub use jump_report::{build_jump_report, JumpReport};
+pub use telemetry_parser_serv::{get_result_metadata_for_file, TelemetryParsedData};
+pub use utils::u_serv::Vector3d;
+
+
+
+/// Parse and analyse one file, `cache_dir_path` and `csv_mapping_path` as in `get_cached_metadata_for_file`
+pub fn analyse_file(src_file: &str, sma_base: usize, cache_dir_path: &str, csv_mapping_path: &str) -> Result<JumpReport, String> {
+    let data = get_cached_metadata_for_file(src_file, cache_dir_path, csv_mapping_path)?;
+    Ok(build_jump_report(&data, sma_base))
+}
+
+pub fn parse_mp4_files(
+    src_files_path_list: &Vec<PathBuf>,
+    cache_dir_path     : &str,
+    csv_mapping_path   : &str,
+) -> Vec<Result<TelemetryParsedData, String>> {
+    let mut result_list:Vec<Result<TelemetryParsedData, String>> = vec![];
+
+    // chapters of one GoPro recording are stitched and parsed once
+    let mut parsed_recordings: Vec<String> = vec![];
+    for src_file_path in src_files_path_list {
+        let src_file = src_file_path.to_string_lossy();
+        let first_chapter = telemetry_parser::filesystem::gopro_chapter_paths(&src_file).swap_remove(0);
+        if parsed_recordings.contains(&first_chapter) {
+            continue;
+        }
+        parsed_recordings.push(first_chapter);
+        let file_res = get_cached_metadata_for_file(&src_file, cache_dir_path, csv_mapping_path);
+        result_list.push(file_res);
+    };
+    result_list
+}
@@ -3,7 +3,7 @@
 #[macro_export]
 macro_rules! promptExit {
     ($msg: expr) => {
-        $crate::utils::u_serv::prompt_to_exit($msg);
+        $crate::prompt_serv::prompt_to_exit($msg);
         return;
     };
 }
@@ -21,18 +21,18 @@ macro_rules! configValues {
             let mut settings = Config::default();
 
             if let Err(e) = settings.merge(Cfg_file::with_name("config.toml")) {
-                println!("Failed to load configuration file: {}", e);
-                println!("default configuration used");
+                eprintln!("Failed to load configuration file: {}", e);
+                eprintln!("default configuration used");
             }
-            println!("Config loaded from file");
+            eprintln!("Config loaded from file");
 
             $(
                 let $var = settings
                     .get::<$type>(stringify!($var))
                     .unwrap_or($default);
-                println!(concat!(stringify!($var), ": {}"), $var);
+                eprintln!(concat!(stringify!($var), ": {}"), $var);
             )*
-            println!();
+            eprintln!();
 
             ConfigValues {
                 $($var),*
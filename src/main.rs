@@ -1,27 +1,19 @@
 
 
-pub mod utils {
-    pub mod error;
-    pub mod u_serv;
-    pub use u_serv::abs_max;
-}
-
 pub mod macros;
-pub mod analise;
-pub mod analise_ev_rnd;
-
-pub mod telemetry_parser_serv;
-pub mod file_sys_serv;
-pub mod plot_serv;
-
+mod prompt_serv;
 mod cli_config;
+mod tui;
+pub mod plot_serv;
+pub mod synth;
+pub mod watch;
 
 
 
 use std::path::PathBuf;
-use std::sync::Mutex;
 
-use analise::{
+use ffmin_parser::{analise_gyro, cache, evaluate, parse_mp4_files, sync, telemetry_parser_serv};
+use ffmin_parser::analise::{
     calc_velocity_arr,
     data_to_stat_vals_arr,
     v3d_list_to_magnitude_sma_list,
@@ -29,9 +21,7 @@ use analise::{
     v3d_list_to_plainsum_sma_list,
     v3d_list_to_ts_sma_v3d_list
 };
-use analise_ev_rnd::stft_result_analise;
 // use file_sys_serv::{save_det_log_to_txt, save_sma_log_to_txt};
-use lazy_static::lazy_static;
 
 use plot_serv::{
     gnu_plot_multi_ts_data,
@@ -39,30 +29,24 @@ use plot_serv::{
     // gnu_plot_stats_for_v3d_data,
 };
 use rfd::FileDialog;
+use telemetry_parser::tags_impl::GroupId;
 use config::{Config, File as Cfg_file};
 
-use cli_config::get_cli_merged_config;
+use cli_config::{get_cli_merged_config, CliCommand};
 
 
 // use file_sys_serv::get_output_filename;
-use telemetry_parser_serv::{get_result_metadata_for_file, TelemetryParsedData, TsScalarArr, TsValsArr};
-
-use utils::u_serv::Vector3d;
-
-
-pub mod analise_log_v;
+use ffmin_parser::telemetry_parser_serv::{TelemetryParsedData, TsScalarArr, TsValsArr};
 
-
-
-lazy_static! {
-    pub static ref SMA_BASE: Mutex<Vec<usize>> = Mutex::new(vec![50]);
-}
+use ffmin_parser::session::{Session, SessionFile, DEF_SMA_BASE};
+use ffmin_parser::utils::u_serv::Vector3d;
 
 
 
 
 
 const DEF_DIR    : &str        = ".";
+const DEF_CACHE_DIR: &str      = ".ffmin_cache";
 const DEP_TIME_CORRECTION: f64 = 2.0;
 const TIME_START_OFFSET  : f64 = -3.0;
 const TIME_END_OFFSET    : f64 = 3.0;
@@ -76,10 +60,12 @@ pub const SAVE_LOG: bool = false;
 configValues!(
     ( srs_dir_path       , String , DEF_DIR.to_string() ),
     ( dest_dir_path      , String , DEF_DIR.to_string() ),
+    ( cache_dir_path     , String , DEF_CACHE_DIR.to_string() ),
     ( dep_time_correction, f64    , DEP_TIME_CORRECTION ),
     ( time_start_offset  , f64    , TIME_START_OFFSET ),
     ( time_end_offset    , f64    , TIME_END_OFFSET ),
-    ( min_accel_trigger  , f64    , MIN_ACCEL_TRIGGER )
+    ( min_accel_trigger  , f64    , MIN_ACCEL_TRIGGER ),
+    ( csv_mapping_path   , String , String::new() )
 );
 
 
@@ -89,49 +75,6 @@ pub fn format_acc_datablock(acc_n_time: &(f64, f64)) -> String {
 
 
 
-pub fn parse_mp4_files(
-    src_files_path_list: &Vec<PathBuf>,
-    // config_values      : ConfigValues
-) -> Vec<Result<TelemetryParsedData, String>> {
-    let mut result_list:Vec<Result<TelemetryParsedData, String>> = vec![];
-
-    for src_file_path in src_files_path_list {
-        let file_res = get_result_metadata_for_file(&src_file_path.to_string_lossy());
-        result_list.push(file_res);
-    };
-    result_list
-}
-
-
-
-
-
-
-
-fn input_sma_base() {
-    let mut base_series = SMA_BASE.lock().unwrap();
-    println!("\ninput base (current {:?})...\n", &base_series);
-    let mut input = String::new();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-
-    let input_vec = input.trim().split_whitespace();
-    if input_vec.clone().count() < 1 {
-        return
-    }
-
-    *base_series = input_vec
-        .map(
-            |s| s.parse::<usize>().unwrap_or(50 as usize)
-        )
-        .collect::<Vec<usize>>()
-}
-
-
-
-
-
 fn plot_parsed_analised_base_series(data: &[Vector3d], base_series: &[usize], title: &str) {
     gnu_plot_v3d_series_and_stats(data, base_series, title);
 }
@@ -227,23 +170,199 @@ fn plot_iso_series(data: &TsScalarArr, base_series: &[usize], title: &str) {
     gnu_plot_multi_ts_data(&iso_series2, title);
 }
 
+/// Accel magnitude SMA per base with the time axis relative to `exit` (sec from the file start)
+fn plot_acc_from_exit(data: &TelemetryParsedData, base_series: &[usize], exit: f64, title: &str) {
+    let magnitude: Vec<f64> = data.acc_data.v.iter().map(Vector3d::magnitude).collect();
+    let t: Vec<f64> = data.acc_data.t.iter().map(|t| t / 1000.0 - exit).collect();
+    let acc_series: Vec<(Vec<f64>, Vec<f64>, usize, &str)> = base_series
+        .iter()
+        .map(|base| (t.clone(), analise_gyro::sma_list(&magnitude, *base), *base, "black"))
+        .collect();
+
+    gnu_plot_multi_ts_data(&acc_series, &format!("{} accel from exit", title));
+}
 
+fn plot_audio_wind_series(data: &TelemetryParsedData, title: &str) {
+    let audio_series: Vec<(Vec<f64>, Vec<f64>, &str, &str)> = vec![
+        (data.wind_data.t.clone()       , data.wind_data.v.clone()       , "wind 0..100"   , "blue"),
+        (data.audio_level_data.t.clone(), data.audio_level_data.v.clone(), "audio rms dBFS", "black"),
+        (data.mic_wet_data.t.clone()    , data.mic_wet_data.v.clone()    , "mic wet"       , "red"),
+    ]
+        .into_iter()
+        .filter(|series| !series.0.is_empty())
+        .collect();
+
+    if !audio_series.is_empty() {
+        gnu_plot_multi_ts_data(&audio_series, title);
+    }
+}
+
+fn plot_gyro_analysis(data: &TelemetryParsedData, base_series: &[usize], title: &str) {
+    if data.gyro_data.v.is_empty() {
+        println!("NO GYRO DATA");
+        return;
+    }
+    plot_parsed_analised_base_series(&data.gyro_data.v, base_series, &format!("{} gyro", title));
 
-fn calculate_deployment(data: &[Vector3d], base_series: &[usize], title: &str) {
-    println!("{}", title);
     for base in base_series {
-        let cur_magnitude_sma = v3d_list_to_magnitude_sma_list(data, *base);
-        stft_result_analise(&cur_magnitude_sma.1, *base)
+        let gyro_analysis = analise_gyro::analise_gyro(data, *base);
+        for spin in &gyro_analysis.spins {
+            println!(
+                "SPIN {:.2}s - {:.2}s: peak {:.0} deg/s, mean {:.0} deg/s, heading change {:+.0} deg",
+                spin.start, spin.end, spin.peak_rate, spin.mean_rate, spin.heading_change
+            );
+        }
+        gnu_plot_multi_ts_data(
+            &[
+                (gyro_analysis.t.clone(), gyro_analysis.rate_magnitude, "|w| deg/s"   , "black"),
+                (gyro_analysis.t.clone(), gyro_analysis.yaw_rate      , "yaw deg/s"   , "blue"),
+                (gyro_analysis.t.clone(), gyro_analysis.heading       , "heading deg" , "red"),
+            ],
+            &format!("{} angular rate, {} pt", title, base),
+        );
+    }
+}
+
+
+fn plot_file_analysis(file: &SessionFile, base_series: &[usize]) {
+    let res_data = &file.data;
+    // plot_parsed_analised_base_series(
+    //     &res_data.acc_data.v,
+    //     &base_series,
+    //     &res_data.file_name,
+    // );
+
+    if let Some(exit) = file.reports.first().and_then(|r| r.exit.as_ref()) {
+        plot_acc_from_exit(
+            res_data,
+            base_series,
+            exit.time,
+            &res_data.file_name,
+        );
+    }
+
+    plot_gyro_analysis(
+        res_data,
+        base_series,
+        &res_data.file_name,
+    );
+
+    // plot_velosity_list(
+    //     &res_data.acc_data,
+    //     &base_series,
+    // );
+
+    plot_iso_series(
+        &res_data.lumen_data,
+        base_series,
+        &res_data.file_name,
+    );
+    plot_audio_wind_series(
+        res_data,
+        &res_data.file_name,
+    );
+}
+
+/// Parses the files and opens them in the TUI, returns the sma bases the session ended with
+fn explore_files(src_files_path_list: &Vec<PathBuf>, sma_bases: Vec<usize>, config_values: &ConfigValues) -> Vec<usize> {
+    let mut data_list = Vec::new();
+    for res in parse_mp4_files(src_files_path_list, &config_values.cache_dir_path, &config_values.csv_mapping_path) {
+        match res {
+            Ok(res_data) => data_list.push(res_data),
+            Err(error)   => println!("ERR: {error}"),
+        }
+    }
+    // if SAVE_LOG {
+    //     save_log_data(&src_files_path_list[0], &res_data);
+    // };
+
+    let mut session = Session::new(data_list, sma_bases);
+    if let Err(e) = tui::run_tui(&mut session, &plot_file_analysis) {
+        println!("ERR: terminal {}", e);
+    }
+    session.sma_bases
+}
+
+
+fn run_cli_command(command: CliCommand, config_values: &ConfigValues) {
+    match command {
+        CliCommand::Evaluate { dir, base, tolerance } => {
+            let dir = dir.unwrap_or(config_values.srs_dir_path.clone());
+            let report = evaluate::evaluate_dir(&dir, base, tolerance);
+            evaluate::print_evaluation_report(&report);
+            match evaluate::save_evaluation_report(&report, &config_values.dest_dir_path) {
+                Ok(report_path) => println!("\nsummary saved to {:?}", report_path),
+                Err(e)          => println!("ERR: failed to save summary {}", e),
+            }
+        },
+        CliCommand::Synth { dir, count, seed } => {
+            let dir = dir.unwrap_or(config_values.dest_dir_path.clone());
+            match synth::save_synthetic_jumps(&dir, count, seed) {
+                Ok(saved) => println!("{} synthetic jumps saved to {}", saved.len(), dir),
+                Err(e)    => println!("ERR: failed to save synthetic jumps {}", e),
+            }
+        },
+        CliCommand::Watch { dir, base, once } => {
+            let dir = dir.unwrap_or(config_values.srs_dir_path.clone());
+            watch::watch_dir(&dir, &config_values.dest_dir_path, base, once);
+        },
+        CliCommand::Sync { files, sample_rate } => {
+            let mut data_list = Vec::new();
+            for file in &files {
+                match cache::get_cached_metadata_for_file(file, &config_values.cache_dir_path, &config_values.csv_mapping_path) {
+                    Ok(data) => data_list.push(data),
+                    Err(e)   => println!("ERR: {} {}", file, e),
+                }
+            }
+            if let Some(result) = sync::sync_parsed_data(&data_list, sample_rate) {
+                sync::print_sync_result(&result);
+                match sync::save_sync_result(&result, &config_values.dest_dir_path) {
+                    Ok(sync_path) => println!("\nsync saved to {:?}", sync_path),
+                    Err(e)        => println!("ERR: failed to save sync {}", e),
+                }
+                match sync::save_sync_edl(&result, &config_values.dest_dir_path) {
+                    Ok(edl_path) => println!("sync markers saved to {:?}", edl_path),
+                    Err(e)       => println!("ERR: failed to save sync markers {}", e),
+                }
+            }
+        },
+        CliCommand::Dump { file, group, tag, from, to, hex, json } => {
+            let filter = telemetry_parser_serv::DumpFilter {
+                group: group.map(|g| g.parse().unwrap_or(GroupId::Custom(g))),
+                tag,
+                from,
+                to,
+                hex,
+                json,
+            };
+            match telemetry_parser_serv::dump_file(&file, &filter, &config_values.csv_mapping_path) {
+                Ok(cnt) => if !json { println!("{} tags", cnt) },
+                Err(e)  => println!("ERR: {} {}", file, e),
+            }
+        },
+        CliCommand::Tui { files } => {
+            let paths: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+            explore_files(&paths, vec![DEF_SMA_BASE], config_values);
+        },
     }
 }
 
 
 fn main() {
-    let mut config_values = get_config_values();
-    config_values = get_cli_merged_config(config_values);
+    let (mut config_values, cli_command) = get_cli_merged_config(get_config_values());
+    if !config_values.csv_mapping_path.is_empty() {
+        if let Err(e) = telemetry_parser::generic_csv::CsvMapping::from_file(&config_values.csv_mapping_path) {
+            eprintln!("ERR: {} {}", config_values.csv_mapping_path, e);
+            config_values.csv_mapping_path.clear();
+        }
+    }
+    if let Some(command) = cli_command {
+        run_cli_command(command, &config_values);
+        return;
+    }
 
+    let mut sma_bases = vec![DEF_SMA_BASE];
     loop {
-        let base_series = SMA_BASE.lock().unwrap().to_owned();
         let src_files_path_list = match FileDialog::new()
             .add_filter("mp4", &["mp4", "MP4"])
             .set_directory(&config_values.srs_dir_path)
@@ -254,49 +373,6 @@ fn main() {
                 }
             };
 
-            let parsing_result = parse_mp4_files(&src_files_path_list);
-            for res in parsing_result {
-                match res {
-                    Ok(res_data) => {
-                        // plot_parsed_analised_base_series(
-                        //     &res_data.acc_data.v,
-                        //     &base_series,
-                        //     &res_data.file_name,
-                        // );
-
-                        calculate_deployment(
-                            &res_data.acc_data.v,
-                            &base_series,
-                            &res_data.file_name,
-                        );
-
-                        // plot_parsed_analised_base_series(
-                        //     &res_data.gyro_data,
-                        //     &base_series,
-                            
-                        //     &res_data.file_name,
-                        // );
-
-                        // plot_velosity_list(
-                        //     &res_data.acc_data,
-                        //     &base_series,
-                        // );
-
-                        plot_iso_series(
-                            &res_data.lumen_data,
-                            &base_series,
-                            &res_data.file_name,
-                        );
-                        // if SAVE_LOG {
-                        //     save_log_data(&src_files_path_list[0], &res_data);
-                        // };
-                    },
-                    Err(error)  => println!("ERR: {error}"),
-                }
-            }
-
-            input_sma_base();
+            sma_bases = explore_files(&src_files_path_list, sma_bases, &config_values);
     }
 }
-
-
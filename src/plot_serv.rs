@@ -7,7 +7,7 @@ use gnuplot::{
 };
 
 
-use crate::utils::u_serv::Vector3d;
+use ffmin_parser::utils::u_serv::Vector3d;
 
 // Into<f64> + Add<Output=T> + Div<Output=T> + Copy>
 
@@ -0,0 +1,16 @@
+pub fn prompt_to_exit(msg: &str) {
+    println!("{}\nPress 'enter' to exit...\n", {msg});
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+}
+
+#[allow(dead_code)]
+pub fn prompt_to_continue(msg: &str) {
+    println!("{}\nPress 'enter' to continue...\n", {msg});
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+}
@@ -0,0 +1,133 @@
+use crate::analise_freefall::STABLE_MAX_RATE;
+use crate::analise_gyro::sma_list;
+use crate::detectors::{default_detectors, Detector};
+use crate::jump_report::{build_jump_report_with, JumpReport};
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+pub const DEF_SMA_BASE: usize = 50;
+pub const STABLE_MAX_RATE_PARAM: &str = "stable_max_rate";
+
+
+/// Parsed file with the analysis results, one report and accel magnitude series per sma base
+pub struct SessionFile {
+    pub data   : TelemetryParsedData,
+    pub reports: Vec<JumpReport>,
+    /// (sec from the file start, accel magnitude SMA m/s2)
+    pub acc_sma: Vec<(Vec<f64>, Vec<f64>)>,
+}
+
+/// Editable analysis parameter, `detector` is `None` for the session own ones
+#[derive(Debug, Clone)]
+pub struct SessionParam {
+    pub detector: Option<&'static str>,
+    pub name    : &'static str,
+    pub value   : f64,
+}
+
+impl SessionParam {
+    pub fn label(&self) -> String {
+        match self.detector {
+            Some(detector) => format!("{}.{}", detector, self.name),
+            None           => self.name.to_string(),
+        }
+    }
+}
+
+
+/// Interactive analysis state, the files are parsed once and re-analysed on every parameter change
+pub struct Session {
+    pub files          : Vec<SessionFile>,
+    pub sma_bases      : Vec<usize>,
+    pub detectors      : Vec<Box<dyn Detector>>,
+    pub stable_max_rate: f64,
+}
+
+
+
+/// Whitespace separated sma bases, every one of them must be a positive integer
+pub fn parse_sma_bases(input: &str) -> Result<Vec<usize>, String> {
+    let bases = input
+        .split_whitespace()
+        .map(|s| match s.parse::<usize>() {
+            Ok(base) if base > 0 => Ok(base),
+            _                    => Err(format!("invalid sma base '{}', expected a positive integer", s)),
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+    if bases.is_empty() {
+        return Err("no sma bases".to_string());
+    }
+    Ok(bases)
+}
+
+
+
+impl Session {
+    pub fn new(data_list: Vec<TelemetryParsedData>, sma_bases: Vec<usize>) -> Self {
+        let mut session = Session {
+            files: data_list
+                .into_iter()
+                .map(|data| SessionFile { data, reports: Vec::new(), acc_sma: Vec::new() })
+                .collect(),
+            sma_bases,
+            detectors      : default_detectors(),
+            stable_max_rate: STABLE_MAX_RATE,
+        };
+        session.reanalyse();
+        session
+    }
+
+    pub fn reanalyse(&mut self) {
+        for file in self.files.iter_mut() {
+            let t: Vec<f64> = file.data.acc_data.t.iter().map(|t| t / 1000.0).collect();
+            let magnitude: Vec<f64> = file.data.acc_data.v.iter().map(|v| v.magnitude()).collect();
+
+            file.reports = self.sma_bases
+                .iter()
+                .map(|base| build_jump_report_with(&file.data, *base, &self.detectors, self.stable_max_rate))
+                .collect();
+            file.acc_sma = self.sma_bases
+                .iter()
+                .map(|base| (t.clone(), sma_list(&magnitude, *base)))
+                .collect();
+        }
+    }
+
+    pub fn set_sma_bases(&mut self, input: &str) -> Result<(), String> {
+        self.sma_bases = parse_sma_bases(input)?;
+        self.reanalyse();
+        Ok(())
+    }
+
+    pub fn params(&self) -> Vec<SessionParam> {
+        let mut params = vec![SessionParam {
+            detector: None,
+            name    : STABLE_MAX_RATE_PARAM,
+            value   : self.stable_max_rate,
+        }];
+        for detector in &self.detectors {
+            params.extend(detector.params().into_iter().map(|(name, value)| SessionParam {
+                detector: Some(detector.name()),
+                name,
+                value,
+            }));
+        }
+        params
+    }
+
+    pub fn set_param(&mut self, param: &SessionParam, value: f64) -> Result<(), String> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!("invalid {} value {}", param.label(), value));
+        }
+        match param.detector {
+            None => self.stable_max_rate = value,
+            Some(name) => match self.detectors.iter_mut().find(|d| d.name() == name) {
+                Some(detector) => detector.set_param(param.name, value),
+                None           => return Err(format!("no detector {}", name)),
+            },
+        }
+        self.reanalyse();
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustfft::{FftPlanner, num_complex::Complex};
+use serde::Serialize;
+
+use crate::analise_gyro::sma_list;
+use crate::telemetry_parser_serv::TelemetryParsedData;
+
+
+
+pub const SYNC_SAMPLE_RATE: f64 = 50.0;   // Hz
+/// removes gravity and drag from the accel magnitude, sec
+pub const DETREND_WINDOW  : f64 = 2.0;
+/// shortest overlap of two files accepted for the correlation peak, sec
+pub const MIN_OVERLAP     : f64 = 10.0;
+/// frame rate of the EDL timecodes
+pub const EDL_FPS         : u32 = 30;
+/// EDL timeline start, negative offsets stay after it
+pub const EDL_START       : f64 = 3600.0;  // sec
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncItem {
+    pub file_name    : String,
+    pub camera_model : String,
+    pub camera_serial: Option<String>,
+    /// add to the file time to get the reference time, sec
+    pub offset       : f64,
+    /// normalized cross-correlation at `offset`, 0..1
+    pub confidence   : f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncResult {
+    pub created_unix: u64,
+    pub reference   : String,
+    pub sample_rate : f64,
+    pub items       : Vec<SyncItem>,
+}
+
+
+
+/// Detrended accel magnitude on a uniform `sample_rate` grid from the first accel sample
+pub fn resample_acc_magnitude(data: &TelemetryParsedData, sample_rate: f64) -> (f64, Vec<f64>) {
+    let t: Vec<f64> = data.acc_data.t.iter().map(|t| t / 1000.0).collect();
+    let magnitude: Vec<f64> = data.acc_data.v.iter().map(|v| v.magnitude()).collect();
+    let len = t.len().min(magnitude.len());
+    if len < 2 {
+        return (0.0, Vec::new());
+    }
+
+    let t_start = t[0];
+    let cnt = ((t[len - 1] - t_start) * sample_rate) as usize + 1;
+    let mut resampled = Vec::with_capacity(cnt);
+    let mut j = 0;
+    for i in 0..cnt {
+        let ti = t_start + i as f64 / sample_rate;
+        while j + 2 < len && t[j + 1] < ti {
+            j += 1;
+        }
+        let dt = t[j + 1] - t[j];
+        let k = if dt > 0.0 { ((ti - t[j]) / dt).clamp(0.0, 1.0) } else { 0.0 };
+        resampled.push(magnitude[j] + (magnitude[j + 1] - magnitude[j]) * k);
+    }
+
+    let trend = sma_list(&resampled, (DETREND_WINDOW * sample_rate) as usize);
+    (t_start, resampled.iter().zip(trend.iter()).map(|(v, tr)| v - tr).collect())
+}
+
+fn z_normalized(data: &[f64]) -> Vec<f64> {
+    let n = data.len().max(1) as f64;
+    let mean = data.iter().fold(0.0, |acc, v| acc + v) / n;
+    let std  = (data.iter().fold(0.0, |acc, v| acc + (v - mean).powi(2)) / n).sqrt();
+    data.iter().map(|v| if std > 0.0 { (v - mean) / std } else { 0.0 }).collect()
+}
+
+/// `sum(a[i + lag] * b[i])` for every lag from `-(b.len() - 1)` to `a.len() - 1`, via FFT
+fn cross_correlation(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let size = (a.len() + b.len()).next_power_of_two();
+    let mut planner = FftPlanner::new();
+    let fft  = planner.plan_fft_forward(size);
+    let ifft = planner.plan_fft_inverse(size);
+
+    let mut fa: Vec<Complex<f64>> = (0..size).map(|i| Complex::new(*a.get(i).unwrap_or(&0.0), 0.0)).collect();
+    let mut fb: Vec<Complex<f64>> = (0..size).map(|i| Complex::new(*b.get(i).unwrap_or(&0.0), 0.0)).collect();
+    fft.process(&mut fa);
+    fft.process(&mut fb);
+    let mut corr: Vec<Complex<f64>> = fa.iter().zip(fb.iter()).map(|(x, y)| x * y.conj()).collect();
+    ifft.process(&mut corr);
+
+    // negative lags are wrapped to the end of the buffer
+    let mut result = Vec::with_capacity(a.len() + b.len() - 1);
+    for lag in -(b.len() as i64 - 1)..(a.len() as i64) {
+        let idx = if lag < 0 { (size as i64 + lag) as usize } else { lag as usize };
+        result.push(corr[idx].re / size as f64);
+    }
+    result
+}
+
+/// Pearson correlation of the overlapping part of `a` and `b` shifted by `lag` samples
+fn overlap_ncc(a: &[f64], b: &[f64], lag: i64) -> f64 {
+    let pairs: Vec<(f64, f64)> = (0..b.len() as i64)
+        .filter(|i| i + lag >= 0 && ((i + lag) as usize) < a.len())
+        .map(|i| (a[(i + lag) as usize], b[i as usize]))
+        .collect();
+    if pairs.len() < 2 {
+        return 0.0;
+    }
+    let n = pairs.len() as f64;
+    let (ma, mb) = pairs.iter().fold((0.0, 0.0), |(sa, sb), (x, y)| (sa + x / n, sb + y / n));
+    let (cov, va, vb) = pairs.iter().fold((0.0, 0.0, 0.0), |(c, va, vb), (x, y)| {
+        (c + (x - ma) * (y - mb), va + (x - ma).powi(2), vb + (y - mb).powi(2))
+    });
+    if va > 0.0 && vb > 0.0 { cov / (va * vb).sqrt() } else { 0.0 }
+}
+
+/// Offset (sec) to add to the `other` time to get the `reference` time, with the confidence
+pub fn find_offset(reference: &TelemetryParsedData, other: &TelemetryParsedData, sample_rate: f64) -> Option<(f64, f64)> {
+    let (ref_start, ref_sig) = resample_acc_magnitude(reference, sample_rate);
+    let (oth_start, oth_sig) = resample_acc_magnitude(other, sample_rate);
+    if ref_sig.is_empty() || oth_sig.is_empty() {
+        return None;
+    }
+    let (a, b) = (z_normalized(&ref_sig), z_normalized(&oth_sig));
+    let corr = cross_correlation(&a, &b);
+
+    let min_overlap = (MIN_OVERLAP * sample_rate) as i64;
+    let first_lag = -(b.len() as i64 - 1);
+    let (lag, _) = corr.iter()
+        .enumerate()
+        .map(|(i, c)| (first_lag + i as i64, *c))
+        .filter_map(|(lag, c)| {
+            let overlap = (a.len() as i64).min(lag + b.len() as i64) - lag.max(0);
+            if overlap >= min_overlap { Some((lag, c / overlap as f64)) } else { None }
+        })
+        .max_by(|x, y| x.1.total_cmp(&y.1))?;
+
+    let confidence = overlap_ncc(&a, &b, lag).max(0.0);
+    Some((ref_start - oth_start + lag as f64 / sample_rate, confidence))
+}
+
+
+
+/// Offsets of every input relative to the first one
+pub fn sync_parsed_data(data_list: &[TelemetryParsedData], sample_rate: f64) -> Option<SyncResult> {
+    let reference = data_list.first()?;
+    let items = data_list.iter().map(|data| {
+        let (offset, confidence) = if std::ptr::eq(data, reference) {
+            (0.0, 1.0)
+        } else {
+            find_offset(reference, data, sample_rate).unwrap_or((0.0, 0.0))
+        };
+        SyncItem {
+            file_name    : data.file_name.clone(),
+            camera_model : data.cam_info.model.clone(),
+            camera_serial: data.cam_info.serial.clone(),
+            offset,
+            confidence,
+        }
+    }).collect();
+
+    Some(SyncResult {
+        created_unix: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        reference   : reference.file_name.clone(),
+        sample_rate,
+        items,
+    })
+}
+
+pub fn print_sync_result(result: &SyncResult) {
+    println!("\nSYNC reference {}", result.reference);
+    for item in &result.items {
+        println!("    {:+10.3}s  confidence {:.2}  {} [{}]",
+            item.offset,
+            item.confidence,
+            item.file_name,
+            item.camera_serial.as_deref().unwrap_or(&item.camera_model),
+        );
+    }
+}
+
+pub fn save_sync_result(result: &SyncResult, dest_dir_path: &str) -> std::io::Result<PathBuf> {
+    let mut dest_dir = PathBuf::from(dest_dir_path);
+    if !dest_dir.exists() {
+        dest_dir = PathBuf::from(".");
+    }
+    let sync_path = dest_dir.join(format!("sync_{}.json", result.created_unix));
+    let sync_json = serde_json::to_string_pretty(result).map_err(std::io::Error::other)?;
+    fs::write(&sync_path, sync_json)?;
+    Ok(sync_path)
+}
+
+/// `HH:MM:SS:FF` at `EDL_FPS`
+fn edl_timecode(sec: f64) -> String {
+    let frames = (sec * EDL_FPS as f64).round().max(0.0) as u64;
+    let fps = EDL_FPS as u64;
+    format!("{:02}:{:02}:{:02}:{:02}", frames / (3600 * fps), frames / (60 * fps) % 60, frames / fps % 60, frames % fps)
+}
+
+/// CMX 3600 marker list: one marker per file where its first frame falls on the reference timeline
+/// (DaVinci Resolve: Timelines > Import > Timeline Markers from EDL)
+pub fn sync_result_to_edl(result: &SyncResult) -> String {
+    let mut edl = format!("TITLE: sync_{}\nFCM: NON-DROP FRAME\n\n", result.created_unix);
+    for (i, item) in result.items.iter().enumerate() {
+        let start = edl_timecode(EDL_START + item.offset);
+        let end   = edl_timecode(EDL_START + item.offset + 1.0 / EDL_FPS as f64);
+        let name  = std::path::Path::new(&item.file_name).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(item.file_name.clone());
+        edl.push_str(&format!("{:03}  001      V     C        {} {} {} {}\n", i + 1, start, end, start, end));
+        edl.push_str(&format!(" |C:ResolveColorBlue |M:{} {:+.3}s confidence {:.2} |D:1\n\n", name, item.offset, item.confidence));
+    }
+    edl
+}
+
+pub fn save_sync_edl(result: &SyncResult, dest_dir_path: &str) -> std::io::Result<PathBuf> {
+    let mut dest_dir = PathBuf::from(dest_dir_path);
+    if !dest_dir.exists() {
+        dest_dir = PathBuf::from(".");
+    }
+    let edl_path = dest_dir.join(format!("sync_{}.edl", result.created_unix));
+    fs::write(&edl_path, sync_result_to_edl(result))?;
+    Ok(edl_path)
+}
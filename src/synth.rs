@@ -0,0 +1,316 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ffmin_parser::analise_landing::LANDING_MIN_PEAK_G;
+use ffmin_parser::evaluate::{get_labels_path, JumpLabels};
+use ffmin_parser::telemetry_parser_serv::{CameraInfo, TelemetryParsedData, TsGpsArr, TsQuatArr, TsScalarArr, TsV3Arr};
+use ffmin_parser::utils::u_serv::Vector3d;
+
+
+
+const G: f64 = 9.80665;
+const CANOPY_AIRSPEED: f64 = 12.0;
+
+
+/// Canopy turn: start (sec from opening), duration (sec), peak yaw rate (deg/s, negative = left)
+#[derive(Debug, Clone)]
+pub struct SynthTurn {
+    pub start   : f64,
+    pub duration: f64,
+    pub rate    : f64,
+}
+
+/// Jump timeline and signal levels, accel in m/s2, durations in seconds
+#[derive(Debug, Clone)]
+pub struct JumpProfile {
+    pub seed            : u64,
+    pub sample_rate     : f64,
+    pub climb_duration  : f64,
+    pub aircraft_vib_freq: f64,
+    pub aircraft_vib_amp: f64,
+    pub freefall_duration: f64,
+    pub freefall_noise  : f64,
+    pub opening_duration: f64,
+    pub opening_peak_g  : f64,
+    pub canopy_duration : f64,
+    pub canopy_turns    : Vec<SynthTurn>,
+    pub landing_peak_g  : f64,
+    pub ground_duration : f64,
+}
+
+impl Default for JumpProfile {
+    fn default() -> Self {
+        Self {
+            seed             : 1,
+            sample_rate      : 200.0,
+            climb_duration   : 40.0,
+            aircraft_vib_freq: 27.0,
+            aircraft_vib_amp : 1.5,
+            freefall_duration: 45.0,
+            freefall_noise   : 2.0,
+            opening_duration : 3.0,
+            opening_peak_g   : 4.0,
+            canopy_duration  : 90.0,
+            canopy_turns     : vec![
+                SynthTurn { start: 20.0, duration: 6.0, rate:  60.0 },
+                SynthTurn { start: 45.0, duration: 8.0, rate: -45.0 },
+                SynthTurn { start: 80.0, duration: 4.0, rate: 120.0 },
+            ],
+            landing_peak_g   : 3.0,
+            ground_duration  : 10.0,
+        }
+    }
+}
+
+impl JumpProfile {
+    /// Default profile with the timeline and levels randomly varied by `seed`
+    pub fn randomized(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let def = Self::default();
+        Self {
+            seed,
+            climb_duration   : rng.gen_range(20.0..60.0),
+            aircraft_vib_freq: rng.gen_range(18.0..40.0),
+            freefall_duration: rng.gen_range(30.0..60.0),
+            opening_duration : rng.gen_range(2.0..5.0),
+            opening_peak_g   : rng.gen_range(2.5..6.0),
+            // the impact stays findable by `detect_landing` above the canopy noise
+            landing_peak_g   : rng.gen_range(LANDING_MIN_PEAK_G + 0.3..5.0),
+            ..def
+        }
+    }
+
+    pub fn deployment_time(&self) -> f64 {
+        self.climb_duration + self.freefall_duration + self.opening_duration / 2.0
+    }
+    pub fn landing_time(&self) -> f64 {
+        self.climb_duration + self.freefall_duration + self.opening_duration + self.canopy_duration
+    }
+    pub fn total_duration(&self) -> f64 {
+        self.landing_time() + self.ground_duration
+    }
+}
+
+
+pub struct SynthJump {
+    pub profile  : JumpProfile,
+    pub acc_data : TsV3Arr,
+    pub gyro_data: TsV3Arr,
+    pub labels   : JumpLabels,
+}
+
+
+
+fn gauss(rng: &mut StdRng, sigma: f64) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+fn noise_v3d(rng: &mut StdRng, sigma: f64) -> Vector3d {
+    Vector3d::new(gauss(rng, sigma), gauss(rng, sigma), gauss(rng, sigma))
+}
+
+/// 0..1..0 bump of the given width centered at 0
+fn bump(dt: f64, width: f64) -> f64 {
+    if dt.abs() >= width / 2.0 { 0.0 } else { (PI * dt / width).cos().powi(2) }
+}
+
+
+/// Accel is camera-frame specific force (z up, 1 g static), gyro in deg/s.
+/// Labels: exit at the end of the climb, deployment at the opening shock peak,
+/// landing at the impact peak.
+pub fn generate_jump(profile: &JumpProfile) -> SynthJump {
+    let mut rng = StdRng::seed_from_u64(profile.seed);
+    let tick = 1.0 / profile.sample_rate;
+    let samples_cnt = (profile.total_duration() * profile.sample_rate) as usize;
+
+    let exit_t       = profile.climb_duration;
+    let opening_t    = exit_t + profile.freefall_duration;
+    let deployment_t = profile.deployment_time();
+    let canopy_t     = opening_t + profile.opening_duration;
+    let landing_t    = profile.landing_time();
+
+    let mut acc_data  = TsV3Arr::new_with_capacity(samples_cnt);
+    let mut gyro_data = TsV3Arr::new_with_capacity(samples_cnt);
+
+    for i in 0..samples_cnt {
+        let t = i as f64 * tick;
+        let (acc, gyro) = if t < exit_t {
+            let vib = profile.aircraft_vib_amp * (2.0 * PI * profile.aircraft_vib_freq * t).sin();
+            (
+                Vector3d::new(0.3 * vib, 0.5 * vib, G + vib).v3add(&noise_v3d(&mut rng, 0.3)),
+                noise_v3d(&mut rng, 2.0),
+            )
+        } else if t < opening_t {
+            // drag builds up to 1 g in a few seconds after the exit
+            let drag = G * (1.0 - (-(t - exit_t) / 3.0).exp());
+            (
+                Vector3d::new(0.0, 0.0, drag).v3add(&noise_v3d(&mut rng, profile.freefall_noise)),
+                noise_v3d(&mut rng, 15.0),
+            )
+        } else if t < canopy_t {
+            let shock = bump(t - deployment_t, profile.opening_duration);
+            (
+                Vector3d::new(0.0, 0.0, G + shock * (profile.opening_peak_g - 1.0) * G)
+                    .v3add(&noise_v3d(&mut rng, profile.freefall_noise * (1.0 + shock))),
+                Vector3d::new(0.0, 0.0, 180.0 * shock).v3add(&noise_v3d(&mut rng, 20.0 * (1.0 + shock))),
+            )
+        } else if t < landing_t + 0.5 {
+            let yaw_rate: f64 = profile.canopy_turns
+                .iter()
+                .map(|turn| turn.rate * bump(t - canopy_t - turn.start - turn.duration / 2.0, turn.duration))
+                .sum();
+            let centripetal = CANOPY_AIRSPEED * yaw_rate.to_radians();
+            let impact = bump(t - landing_t, 0.4) * (profile.landing_peak_g - 1.0) * G;
+            (
+                Vector3d::new(centripetal, 0.0, G + impact).v3add(&noise_v3d(&mut rng, 0.8)),
+                Vector3d::new(0.0, 0.0, yaw_rate).v3add(&noise_v3d(&mut rng, 3.0)),
+            )
+        } else {
+            (
+                Vector3d::new(0.0, 0.0, G).v3add(&noise_v3d(&mut rng, 0.05)),
+                noise_v3d(&mut rng, 0.3),
+            )
+        };
+
+        acc_data.t.push(t * 1000.0);
+        acc_data.v.push(acc);
+        gyro_data.t.push(t * 1000.0);
+        gyro_data.v.push(gyro);
+    }
+
+    SynthJump {
+        profile  : profile.clone(),
+        acc_data,
+        gyro_data,
+        labels   : JumpLabels {
+            exit      : Some(exit_t),
+            deployment: Some(deployment_t),
+            landing   : Some(landing_t),
+        },
+    }
+}
+
+
+
+impl SynthJump {
+    pub fn into_parsed_data(self, file_name: &str) -> TelemetryParsedData {
+        TelemetryParsedData {
+            file_name : file_name.to_string(),
+            cam_info  : CameraInfo { model: "Synthetic".into(), serial: Some(format!("SYNTH{}", self.profile.seed)), recovered: false },
+            acc_data  : self.acc_data,
+            gyro_data : self.gyro_data,
+            lumen_data: TsScalarArr::new_with_capacity(0),
+            audio_level_data: TsScalarArr::new_with_capacity(0),
+            wind_data       : TsScalarArr::new_with_capacity(0),
+            mic_wet_data    : TsScalarArr::new_with_capacity(0),
+            orientation_data: TsQuatArr::new_with_capacity(0),
+            gps_data        : TsGpsArr::new_with_capacity(0),
+        }
+    }
+
+    /// Gyroflow `.gcsv` readable by `telemetry_parser` (gyro rad/s, accel g)
+    pub fn save_gcsv(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "GYROFLOW IMU LOG")?;
+        writeln!(file, "version,1.3")?;
+        writeln!(file, "id,synthetic_jump_{}", self.profile.seed)?;
+        writeln!(file, "orientation,XYZ")?;
+        writeln!(file, "tscale,0.001")?;
+        writeln!(file, "gscale,1.0")?;
+        writeln!(file, "ascale,1.0")?;
+        writeln!(file, "t,gx,gy,gz,ax,ay,az")?;
+        for (i, t) in self.acc_data.t.iter().enumerate() {
+            let gyro = self.gyro_data.v[i].apply_for_all_axis(f64::to_radians);
+            let acc  = self.acc_data.v[i].apply_for_all_axis(|v| v / G);
+            writeln!(
+                file,
+                "{:.3},{:.6},{:.6},{:.6},{:.5},{:.5},{:.5}",
+                t, gyro.x, gyro.y, gyro.z, acc.x, acc.y, acc.z
+            )?;
+        }
+        file.flush()
+    }
+
+    pub fn save_labels(&self, video_path: &Path) -> std::io::Result<PathBuf> {
+        let labels_path = get_labels_path(video_path);
+        let labels_str = toml::to_string(&self.labels).map_err(std::io::Error::other)?;
+        std::fs::write(&labels_path, labels_str)?;
+        Ok(labels_path)
+    }
+}
+
+
+
+/// Writes `count` randomized jumps as `.gcsv` + `.labels.toml` pairs, ready for `evaluate`
+pub fn save_synthetic_jumps(dest_dir_path: &str, count: usize, seed: u64) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dest_dir_path)?;
+    let mut saved = Vec::with_capacity(count);
+    for i in 0..count as u64 {
+        let jump = generate_jump(&JumpProfile::randomized(seed + i));
+        let path = Path::new(dest_dir_path).join(format!("synthetic_jump_{}.gcsv", seed + i));
+        jump.save_gcsv(&path)?;
+        jump.save_labels(&path)?;
+        saved.push(path);
+    }
+    Ok(saved)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ffmin_parser::analise_canopy::CANOPY_START_OFFSET;
+    use ffmin_parser::analise_exit::detect_exit;
+    use ffmin_parser::analise_landing::detect_landing;
+    use ffmin_parser::detectors::{default_detectors, fuse_detector_events};
+    use ffmin_parser::evaluate::DEF_TOLERANCE;
+    use ffmin_parser::session::DEF_SMA_BASE;
+
+    fn assert_near(kind: &str, predicted: Option<f64>, expected: Option<f64>) {
+        let (predicted, expected) = (predicted.expect(kind), expected.expect(kind));
+        assert!((predicted - expected).abs() <= DEF_TOLERANCE, "{kind}: predicted {predicted:.2}, labelled {expected:.2}");
+    }
+
+    fn check_jump(profile: &JumpProfile) {
+        let jump   = generate_jump(profile);
+        let labels = jump.labels.clone();
+        let data   = jump.into_parsed_data("synthetic");
+
+        let fused_events = fuse_detector_events(&default_detectors(), &data, DEF_SMA_BASE);
+        let deployment   = fused_events.first().map(|ev| ev.time);
+        assert_near("deployment", deployment, labels.deployment);
+
+        let exit = detect_exit(&data, deployment).map(|exit| exit.time);
+        assert_near("exit", exit, labels.exit);
+
+        let landing = detect_landing(&data, deployment.unwrap() + CANOPY_START_OFFSET).map(|landing| landing.time);
+        assert_near("landing", landing, labels.landing);
+    }
+
+    #[test]
+    fn default_profile_events_match_labels() {
+        check_jump(&JumpProfile::default());
+    }
+
+    #[test]
+    fn randomized_profile_events_match_labels() {
+        for seed in 1..=5 {
+            check_jump(&JumpProfile::randomized(seed));
+        }
+    }
+
+    #[test]
+    fn randomized_landing_is_findable() {
+        for seed in 0..100 {
+            assert!(JumpProfile::randomized(seed).landing_peak_g >= LANDING_MIN_PEAK_G);
+        }
+    }
+}
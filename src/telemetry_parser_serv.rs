@@ -2,24 +2,33 @@
 
 
 // use std::time::Instant;
+use std::io::Write;
 use std::sync::{ Arc, atomic::AtomicBool };
 // use std::ops::{Add, Div};
 
 use telemetry_parser::Input as TpInput;
 use telemetry_parser::util as tp_util;
+use telemetry_parser::filesystem as tp_filesystem;
 use telemetry_parser::try_block;
 use telemetry_parser::gopro::GoPro;
+use telemetry_parser::generic_csv::CsvMapping;
 use telemetry_parser::tags_impl::{
     GroupId,
     TagId,
     TagValue,
+    TagDescription,
     GetWithType,
+    GpsData,
+    Quaternion,
 };
 
 
+use serde::{Deserialize, Serialize};
+
 use crate::utils::u_serv::Vector3d;
 
 
+#[derive(Serialize, Deserialize)]
 pub struct TsValsArr<T: Clone> {
     pub t: Vec<f64>,
     pub v: Vec<T>,
@@ -59,27 +68,43 @@ impl<T: Clone> TsValsArr<T> {
 
 
 
+#[derive(Serialize, Deserialize)]
 pub struct CameraInfo {
-    pub model : String,
-    pub serial: Option<String>,
+    pub model    : String,
+    pub serial   : Option<String>,
+    /// the file had no index, the telemetry was recovered from the raw data
+    #[serde(default)]
+    pub recovered: bool,
 }
 
 
 pub type TsV3Arr     = TsValsArr<Vector3d>;
 pub type TsScalarArr = TsValsArr<f64>;
+pub type TsQuatArr   = TsValsArr<Quaternion<f64>>;
+pub type TsGpsArr    = TsValsArr<GpsData>;
 
+#[derive(Serialize, Deserialize)]
 pub struct TelemetryParsedData {
     pub file_name : String,
     pub cam_info  : CameraInfo,
     pub acc_data  : TsV3Arr,
     pub gyro_data : TsV3Arr,
     pub lumen_data: TsScalarArr,
+    pub audio_level_data: TsScalarArr,
+    pub wind_data       : TsScalarArr,
+    pub mic_wet_data    : TsScalarArr,
+    pub orientation_data: TsQuatArr,
+    pub gps_data        : TsGpsArr,
 }
 
 
 
 pub const DEF_TICK: f64 = 0.005;
 
+pub const AUDIO_LEVEL_GROUP: &str = "AGCAudioLevel";
+pub const WIND_GROUP       : &str = "WindProcessing";
+pub const MIC_WET_GROUP    : &str = "MicrophoneWet";
+
 
 
 fn convert_array_to_scaled_f64<T: Into<f64> + Copy>(arr: &[T], scale: f64) -> Vec<f64> {
@@ -89,18 +114,109 @@ fn convert_array_to_scaled_f64<T: Into<f64> + Copy>(arr: &[T], scale: f64) -> Ve
 }
 
 
-#[allow(unused)]
-fn dump_samples(samples: &[tp_util::SampleInfo]) {
-    for info in samples {
-        if info.tag_map.is_none() { continue; }
-        let grouped_tag_map = info.tag_map.as_ref().unwrap();
+/// Sample tags selection for `dump_samples`, time range in seconds from the file start
+#[derive(Debug, Default, Clone)]
+pub struct DumpFilter {
+    pub group: Option<GroupId>,
+    /// `TagId` name or the native tag description (GoPro 4CC)
+    pub tag  : Option<String>,
+    pub from : Option<f64>,
+    pub to   : Option<f64>,
+    /// hex of `raw_data` along with the value
+    pub hex  : bool,
+    /// one JSON object per tag
+    pub json : bool,
+}
+
+impl DumpFilter {
+    fn matches_time(&self, info: &tp_util::SampleInfo) -> bool {
+        let t = info.timestamp_ms / 1000.0;
+        self.from.map(|from| t + info.duration_ms / 1000.0 >= from).unwrap_or(true)
+        && self.to.map(|to| t <= to).unwrap_or(true)
+    }
+
+    fn matches_tag(&self, group: &GroupId, taginfo: &TagDescription) -> bool {
+        let group_ok = match &self.group {
+            None | Some(GroupId::Any) => true,
+            Some(g)                   => g == group,
+        };
+        let tag_ok = match &self.tag {
+            None      => true,
+            Some(tag) => tag == "*"
+                || tag.parse::<TagId>().map(|id| id == taginfo.id).unwrap_or(false)
+                || tag.eq_ignore_ascii_case(&taginfo.description),
+        };
+        group_ok && tag_ok
+    }
+}
+
+#[derive(Serialize)]
+struct DumpRecord<'a> {
+    sample_index: u64,
+    timestamp_ms: f64,
+    group       : &'a GroupId,
+    tag         : &'a TagId,
+    native_id   : Option<u32>,
+    description : &'a str,
+    value       : &'a TagValue,
+    formatted   : String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_data    : Option<String>,
+}
+
+/// Writes every tag of `samples` selected by `filter` to `out`, returns the number of written tags
+pub fn dump_samples(samples: &[tp_util::SampleInfo], filter: &DumpFilter, out: &mut impl Write) -> std::io::Result<usize> {
+    let mut cnt = 0;
+    for info in samples.iter().filter(|info| filter.matches_time(info)) {
+        let grouped_tag_map = match info.tag_map.as_ref() {
+            Some(map) => map,
+            None      => continue,
+        };
 
         for (group, map) in grouped_tag_map {
-            for (tagid, taginfo) in map {
-                println!("{: <25} {: <20} {: <10}: {}{}", format!("{}", group), format!("{}", tagid), taginfo.description, &taginfo.value.to_string().len(), &taginfo.value.to_string().chars().take(50).collect::<String>());
+            for (tagid, taginfo) in map.iter().filter(|(_, taginfo)| filter.matches_tag(group, taginfo)) {
+                let raw_data = if filter.hex { Some(tp_util::to_hex(taginfo.value.raw_data()).trim_end().to_string()) } else { None };
+                if filter.json {
+                    let record = DumpRecord {
+                        sample_index: info.sample_index,
+                        timestamp_ms: info.timestamp_ms,
+                        group,
+                        tag         : tagid,
+                        native_id   : taginfo.native_id,
+                        description : &taginfo.description,
+                        value       : &taginfo.value,
+                        formatted   : taginfo.value.to_string(),
+                        raw_data,
+                    };
+                    let record_json = serde_json::to_string(&record).map_err(std::io::Error::other)?;
+                    writeln!(out, "{}", record_json)?;
+                } else {
+                    writeln!(out, "{:>10.3}s {: <25} {: <20} {: <10}: {}",
+                        info.timestamp_ms / 1000.0,
+                        format!("{}", group),
+                        format!("{}", tagid),
+                        taginfo.description,
+                        taginfo.value.to_string(),
+                    )?;
+                    if let Some(raw_data) = raw_data {
+                        writeln!(out, "{: <12}raw {} bytes: {}", "", taginfo.value.raw_data().len(), raw_data)?;
+                    }
+                }
+                cnt += 1;
             }
         }
     }
+    Ok(cnt)
+}
+
+pub fn dump_file(src_file: &str, filter: &DumpFilter, csv_mapping_path: &str) -> Result<usize, String> {
+    let input = open_tp_input(src_file, csv_mapping_path)?;
+    let samples = input.samples.as_deref().unwrap_or(&[]);
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    let cnt = dump_samples(samples, filter, &mut out).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())?;
+    Ok(cnt)
 }
 
 fn get_cam_serial(sample_0: &tp_util::SampleInfo) -> Option<String> {
@@ -122,14 +238,19 @@ fn get_cam_info(input: &TpInput) -> CameraInfo {
         cam_model = model.to_string();
     };
     if let Some(samples) = &input.samples {
-        cam_serial = get_cam_serial(&samples[0]);
+        cam_serial = samples.first().and_then(get_cam_serial);
     };
 
     println!("Detected camera: {cam_model} {:?}", &cam_serial);
+    let recovered = input.is_recovered();
+    if recovered {
+        println!("WARN: no MP4 index, telemetry recovered from the raw GPMF data");
+    }
 
     CameraInfo{
-        model : cam_model.into(),
-        serial: cam_serial,
+        model    : cam_model.into(),
+        serial   : cam_serial,
+        recovered,
     }
     // if opts.dump { dump_samples(&samples[0..2])}
     // dump_samples(&samples.clone()[0..1]);
@@ -171,25 +292,141 @@ fn get_iso_data(input: &TpInput) -> std::io::Result<TsScalarArr> {
     Ok(lum_data)
 }
 
+fn get_column_of_values<T: Into<f64> + Copy>(arr: &[Vec<T>], column: usize) -> Vec<f64> {
+    arr.iter()
+        .map(|row| row.get(column).map(|x| (*x).into()).unwrap_or(f64::NAN))
+        .collect()
+}
+
+/// Timeseries of one column of a GPMF custom group (AALP, WNDM, MWET...)
+fn get_custom_group_data(input: &TpInput, group_name: &str, column: usize) -> TsScalarArr {
+    let mut group_data = TsScalarArr::new_with_capacity(10000);
+    let group_id = GroupId::Custom(group_name.to_string());
 
+    if let Some(ref samples) = input.samples {
+        for info in samples {
+            if info.tag_map.is_none() { continue }
+            let duration = info.duration_ms;
+            let grouped_tag_map = info.tag_map.as_ref().unwrap();
 
+            if let Some(taginfo) = grouped_tag_map.get(&group_id).and_then(|map| map.get(&TagId::Data)) {
+                let vals = match &taginfo.value {
+                    TagValue::Vec_Vec_i8(arr)     => get_column_of_values(arr.get(), column),
+                    TagValue::Vec_Vec_u8(arr)     => get_column_of_values(arr.get(), column),
+                    TagValue::Vec_i8(arr)         => convert_array_to_scaled_f64(arr.get(), 1.),
+                    TagValue::Vec_u8(arr)         => convert_array_to_scaled_f64(arr.get(), 1.),
+                    TagValue::Vec_Vector3_u8(arr) => arr.get().iter().map(|v| [v.x, v.y, v.z][column.min(2)] as f64).collect(),
+                    _ => { eprintln!("ERR: not valid {} custom group format {:?}", group_name, taginfo.value); continue; }
+                };
+                if !vals.is_empty() {
+                    group_data.add_vals_by_slice_duration_scaled(&vals, duration, 1000.);
+                }
+            }
+        }
+    }
+    group_data
+}
 
-pub fn parse_telemetry_from_mp4_file(src_file: &str) -> Result<TelemetryParsedData, String> {
-    let mut stream = match std::fs::File::open(src_file) {
-        Ok(stream) => stream,
-        Err(e) => {return Err(e.to_string());},
-    };
+/// GPS fixes, `t` in ms from the sample time,
+/// points inside one sample are spread by their own unix timestamps
+fn get_gps_data(input: &TpInput) -> TsGpsArr {
+    let mut gps_data = TsGpsArr::new_with_capacity(1000);
 
-    let filesize = match stream.metadata() {
-        Ok(metadata) => metadata.len() as usize,
-        Err(e) => {return Err(format!("NO_METADATA! {}", e.to_string()));},
-    };
+    if let Some(ref samples) = input.samples {
+        for info in samples {
+            if info.tag_map.is_none() { continue }
+            let grouped_tag_map = info.tag_map.as_ref().unwrap();
+
+            if let Some(taginfo) = grouped_tag_map.get(&GroupId::GPS).and_then(|map| map.get(&TagId::Data)) {
+                match &taginfo.value {
+                    TagValue::Vec_GpsData(arr) => {
+                        let first_unix = arr.get().first().map(|g| g.unix_timestamp).unwrap_or(0.0);
+                        for gps in arr.get() {
+                            gps_data.t.push(info.timestamp_ms + (gps.unix_timestamp - first_unix) * 1000.0);
+                            gps_data.v.push(gps.clone());
+                        }
+                    },
+                    TagValue::GpsData(gps) => {
+                        gps_data.t.push(info.timestamp_ms);
+                        gps_data.v.push(gps.get().clone());
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+    gps_data
+}
+
+/// Camera orientation quaternions (GoPro CORI*IORI, DJI...), `t` in ms as the IMU data
+fn get_orientation_data(input: &TpInput) -> TsQuatArr {
+    let mut quat_data = TsQuatArr::new_with_capacity(10000);
+
+    if let Some(ref samples) = input.samples {
+        for info in samples {
+            if info.tag_map.is_none() { continue }
+            let grouped_tag_map = info.tag_map.as_ref().unwrap();
+
+            if let Some(taginfo) = grouped_tag_map.get(&GroupId::Quaternion).and_then(|map| map.get(&TagId::Data)) {
+                if let TagValue::Vec_TimeQuaternion_f64(arr) = &taginfo.value {
+                    for q in arr.get() {
+                        quat_data.t.push(q.t);
+                        quat_data.v.push(q.v);
+                    }
+                }
+            }
+        }
+    }
+    quat_data
+}
+
+
+
+
+/// Opens a file, every chapter of a GoPro recording as one `TpInput`.
+/// `csv_mapping_path` is the column mapping for CSV logs without a sidecar one, empty for none
+pub fn open_tp_input(src_file: &str, csv_mapping_path: &str) -> Result<TpInput, String> {
+    let chapter_paths = tp_filesystem::gopro_chapter_paths(src_file);
+    if chapter_paths.len() > 1 {
+        eprintln!("{} chapters: {:?}", chapter_paths.len(), chapter_paths);
+        match TpInput::from_chapters(&chapter_paths, |_|(), Arc::new(AtomicBool::new(false))) {
+            Ok(input) => Ok(input),
+            Err(e)    => Err(format!("FAIL TO STITCH CHAPTERS! {}", e)),
+        }
+    } else {
+        let mut stream = match std::fs::File::open(src_file) {
+            Ok(stream) => stream,
+            Err(e) => {return Err(e.to_string());},
+        };
+
+        let filesize = match stream.metadata() {
+            Ok(metadata) => metadata.len() as usize,
+            Err(e) => {return Err(format!("NO_METADATA! {}", e.to_string()));},
+        };
+
+        let csv_mapping = if csv_mapping_path.is_empty() {
+            None
+        } else {
+            Some(CsvMapping::from_file(csv_mapping_path).map_err(|e| format!("BAD CSV MAPPING! {} {}", csv_mapping_path, e))?)
+        };
+
+        TpInput::from_stream_with_csv_mapping(&mut stream, filesize, src_file, csv_mapping.as_ref(), |_|(), Arc::new(AtomicBool::new(false)))
+            .map_err(|e| format!("FAIL TO PARSE! {}", e))
+    }
+}
 
-    let input = TpInput::from_stream(&mut stream, filesize, src_file, |_|(), Arc::new(AtomicBool::new(false))).unwrap();
+pub fn parse_telemetry_from_mp4_file(src_file: &str, csv_mapping_path: &str) -> Result<TelemetryParsedData, String> {
+    let input = open_tp_input(src_file, csv_mapping_path)?;
     let cam_info = get_cam_info(&input);
 
 
     let iso_data = get_iso_data(&input);
+    // AALP [rms_level, peak_level] dBFS, WNDM [wind_enable, meter_value 0..100], MWET [mic_wet, all_mics, confidence]
+    let audio_level_data = get_custom_group_data(&input, AUDIO_LEVEL_GROUP, 0);
+    let wind_data        = get_custom_group_data(&input, WIND_GROUP, 1);
+    let mic_wet_data     = get_custom_group_data(&input, MIC_WET_GROUP, 0);
+    let orientation_data = get_orientation_data(&input);
+    let gps_data         = get_gps_data(&input);
     // let samples = input.samples.clone().unwrap();
     // dump_samples(&samples[..2]);
 
@@ -207,7 +444,7 @@ pub fn parse_telemetry_from_mp4_file(src_file: &str) -> Result<TelemetryParsedDa
             acc_data.t.push(v.timestamp_ms);
             acc_data.v.push(Vector3d::from(vals_arr));
         }
-        if v.magn.is_some() {
+        if v.gyro.is_some() {
             let vals_arr = v.gyro.unwrap_or_default();
             gyro_data.t.push(v.timestamp_ms);
             gyro_data.v.push(Vector3d::from(vals_arr));
@@ -220,18 +457,19 @@ pub fn parse_telemetry_from_mp4_file(src_file: &str) -> Result<TelemetryParsedDa
         acc_data,
         gyro_data,
         lumen_data: iso_data.expect("no iso/exposure data found!"),
+        audio_level_data,
+        wind_data,
+        mic_wet_data,
+        orientation_data,
+        gps_data,
     })
 }
 
-pub fn get_result_metadata_for_file(input_file: &str) -> Result<TelemetryParsedData, String> {
-    let telemetry_data = parse_telemetry_from_mp4_file(input_file)?;
+pub fn get_result_metadata_for_file(input_file: &str, csv_mapping_path: &str) -> Result<TelemetryParsedData, String> {
+    let telemetry_data = parse_telemetry_from_mp4_file(input_file, csv_mapping_path)?;
     Ok(TelemetryParsedData{
         file_name: input_file.to_string(),
-        cam_info : telemetry_data.cam_info,
-        acc_data : telemetry_data.acc_data,
-        gyro_data: telemetry_data.gyro_data,
-        
-        lumen_data : telemetry_data.lumen_data,
+        ..telemetry_data
     })
 }
 
@@ -0,0 +1,408 @@
+use std::io;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::Line;
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use ffmin_parser::jump_report::JumpReport;
+use ffmin_parser::session::{Session, SessionFile};
+
+
+
+const G: f64 = 9.81;
+/// points drawn per chart cell column, braille has 2 dots per cell
+const CHART_POINTS_PER_COL: usize = 4;
+const MIN_VIEW     : f64 = 1.0;   // sec
+const CURSOR_STEPS : f64 = 100.0; // per view width
+const PARAM_STEP   : f64 = 1.1;
+
+const HELP: &str = "tab focus  ↑↓ select  ←→ cursor  z/x zoom  n/N event  b base  s sma bases  e edit  +/- param  g plot  q quit";
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Focus {
+    Files,
+    Params,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum InputTarget {
+    SmaBases,
+    Param(usize),
+}
+
+struct TuiState {
+    focus    : Focus,
+    file_idx : usize,
+    base_idx : usize,
+    param_idx: usize,
+    /// sec from the file start
+    cursor   : f64,
+    /// visible seconds around the cursor, `None` for the whole file
+    view     : Option<f64>,
+    input    : Option<(InputTarget, String)>,
+    status   : String,
+}
+
+
+
+fn file_time_range(file: &SessionFile) -> (f64, f64) {
+    let t = &file.acc_sma.first().map(|s| &s.0[..]).unwrap_or(&[]);
+    (t.first().copied().unwrap_or(0.0), t.last().copied().unwrap_or(0.0))
+}
+
+fn report_events(report: &JumpReport) -> Vec<(&'static str, f64, Color)> {
+    [
+        ("exit"      , report.exit.as_ref().map(|e| e.time), Color::Green),
+        ("deployment", report.deployment                  , Color::Red),
+        ("landing"   , report.landing.as_ref().map(|l| l.time), Color::Yellow),
+    ]
+        .into_iter()
+        .filter_map(|(name, time, color)| time.map(|t| (name, t, color)))
+        .collect()
+}
+
+/// Value of the series at the sample closest to `time`
+fn value_at(t: &[f64], v: &[f64], time: f64) -> Option<f64> {
+    let len = t.len().min(v.len());
+    if len == 0 {
+        return None;
+    }
+    let i = t[..len].partition_point(|x| *x < time).min(len - 1);
+    let i = if i > 0 && (time - t[i - 1]).abs() < (t[i] - time).abs() { i - 1 } else { i };
+    Some(v[i])
+}
+
+
+
+impl TuiState {
+    fn new(session: &Session) -> Self {
+        let cursor = session.files.first().map(|f| file_time_range(f).0).unwrap_or(0.0);
+        TuiState {
+            focus    : Focus::Files,
+            file_idx : 0,
+            base_idx : 0,
+            param_idx: 0,
+            cursor,
+            view     : None,
+            input    : None,
+            status   : HELP.to_string(),
+        }
+    }
+
+    fn view_range(&self, file: &SessionFile) -> (f64, f64) {
+        let (start, end) = file_time_range(file);
+        match self.view {
+            Some(view) if view < end - start => {
+                let a = (self.cursor - view / 2.0).clamp(start, end - view);
+                (a, a + view)
+            },
+            _ => (start, end),
+        }
+    }
+
+    fn move_cursor(&mut self, file: &SessionFile, steps: f64) {
+        let (start, end) = file_time_range(file);
+        let (a, b) = self.view_range(file);
+        self.cursor = (self.cursor + steps * (b - a) / CURSOR_STEPS).clamp(start, end);
+    }
+
+    fn zoom(&mut self, file: &SessionFile, factor: f64) {
+        let (a, b) = self.view_range(file);
+        let (start, end) = file_time_range(file);
+        let view = ((b - a) * factor).max(MIN_VIEW);
+        self.view = if view >= end - start { None } else { Some(view) };
+    }
+
+    fn jump_to_event(&mut self, report: &JumpReport, forward: bool) {
+        let events = report_events(report);
+        let next = if forward {
+            events.iter().map(|e| e.1).filter(|t| *t > self.cursor + 1e-3).min_by(f64::total_cmp)
+        } else {
+            events.iter().map(|e| e.1).filter(|t| *t < self.cursor - 1e-3).max_by(f64::total_cmp)
+        };
+        if let Some(t) = next {
+            self.cursor = t;
+        }
+    }
+
+    fn select_file(&mut self, session: &Session, idx: usize) {
+        self.file_idx = idx;
+        self.view     = None;
+        self.cursor   = session.files.get(idx).map(|f| file_time_range(f).0).unwrap_or(0.0);
+    }
+
+    fn apply_input(&mut self, session: &mut Session, target: InputTarget, text: &str) {
+        let res = match target {
+            InputTarget::SmaBases => session.set_sma_bases(text),
+            InputTarget::Param(idx) => match (session.params().get(idx), text.trim().parse::<f64>()) {
+                (Some(param), Ok(value)) => session.set_param(param, value),
+                (_, Err(_))              => Err(format!("invalid number '{}'", text.trim())),
+                (None, _)                => Err("no parameter selected".to_string()),
+            },
+        };
+        self.base_idx = self.base_idx.min(session.sma_bases.len() - 1);
+        self.status = match res {
+            Ok(()) => "re-analysed".to_string(),
+            Err(e) => format!("ERR: {}", e),
+        };
+    }
+
+    fn step_param(&mut self, session: &mut Session, factor: f64) {
+        let params = session.params();
+        if let Some(param) = params.get(self.param_idx) {
+            self.status = match session.set_param(param, param.value * factor) {
+                Ok(())  => format!("{} = {:.3}", param.label(), session.params()[self.param_idx].value),
+                Err(e)  => format!("ERR: {}", e),
+            };
+        }
+    }
+
+    /// `false` to quit
+    fn handle_key(&mut self, session: &mut Session, key: KeyEvent, plot: &dyn Fn(&SessionFile, &[usize])) -> bool {
+        if let Some((target, mut text)) = self.input.take() {
+            match key.code {
+                KeyCode::Enter     => self.apply_input(session, target, &text),
+                KeyCode::Esc       => self.status = HELP.to_string(),
+                KeyCode::Backspace => { text.pop(); self.input = Some((target, text)); },
+                KeyCode::Char(c)   => { text.push(c); self.input = Some((target, text)); },
+                _                  => self.input = Some((target, text)),
+            }
+            return true;
+        }
+
+        let file_cnt  = session.files.len();
+        let param_cnt = session.params().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab => {
+                self.focus = if self.focus == Focus::Files { Focus::Params } else { Focus::Files };
+            },
+            KeyCode::Up => match self.focus {
+                Focus::Files  => if self.file_idx > 0 { self.select_file(session, self.file_idx - 1) },
+                Focus::Params => self.param_idx = self.param_idx.saturating_sub(1),
+            },
+            KeyCode::Down => match self.focus {
+                Focus::Files  => if self.file_idx + 1 < file_cnt { self.select_file(session, self.file_idx + 1) },
+                Focus::Params => self.param_idx = (self.param_idx + 1).min(param_cnt.saturating_sub(1)),
+            },
+            KeyCode::Char('b') => self.base_idx = (self.base_idx + 1) % session.sma_bases.len(),
+            KeyCode::Char('s') => {
+                let bases = session.sma_bases.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(" ");
+                self.input = Some((InputTarget::SmaBases, bases));
+            },
+            KeyCode::Char('e') if self.focus == Focus::Params => {
+                if let Some(param) = session.params().get(self.param_idx) {
+                    self.input = Some((InputTarget::Param(self.param_idx), param.value.to_string()));
+                }
+            },
+            KeyCode::Char('+') if self.focus == Focus::Params => self.step_param(session, PARAM_STEP),
+            KeyCode::Char('-') if self.focus == Focus::Params => self.step_param(session, 1.0 / PARAM_STEP),
+            _ => {}
+        }
+
+        let file = match session.files.get(self.file_idx) {
+            Some(file) => file,
+            None       => return true,
+        };
+        match key.code {
+            KeyCode::Left      => self.move_cursor(file, -1.0),
+            KeyCode::Right     => self.move_cursor(file, 1.0),
+            KeyCode::PageUp    => self.move_cursor(file, -CURSOR_STEPS / 2.0),
+            KeyCode::PageDown  => self.move_cursor(file, CURSOR_STEPS / 2.0),
+            KeyCode::Home      => self.cursor = file_time_range(file).0,
+            KeyCode::End       => self.cursor = file_time_range(file).1,
+            KeyCode::Char('z') => self.zoom(file, 0.5),
+            KeyCode::Char('x') => self.zoom(file, 2.0),
+            KeyCode::Char('n') => if let Some(r) = file.reports.get(self.base_idx) { self.jump_to_event(r, true) },
+            KeyCode::Char('N') => if let Some(r) = file.reports.get(self.base_idx) { self.jump_to_event(r, false) },
+            KeyCode::Char('g') => {
+                plot(file, &session.sma_bases);
+                self.status = format!("plotted {}", file.data.file_name);
+            },
+            _ => {}
+        }
+        true
+    }
+}
+
+
+
+fn draw_files(frame: &mut Frame, area: Rect, session: &Session, state: &TuiState) {
+    let items: Vec<ListItem> = session.files.iter().map(|file| {
+        let flags = file.reports.get(state.base_idx).map(|r| r.flags.len()).unwrap_or(0);
+        let name = std::path::Path::new(&file.data.file_name)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(file.data.file_name.clone());
+        ListItem::new(if flags > 0 { format!("{} [{}]", name, flags) } else { name })
+    }).collect();
+
+    let mut list_state = ListState::default().with_selected(Some(state.file_idx));
+    let list = List::new(items)
+        .block(Block::bordered().title("files").border_style(focus_style(state.focus == Focus::Files)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+fn draw_summary(frame: &mut Frame, area: Rect, file: &SessionFile, state: &TuiState) {
+    let mut lines: Vec<Line> = Vec::new();
+    if let Some((t, v)) = file.acc_sma.get(state.base_idx) {
+        let report = file.reports.get(state.base_idx);
+        let from_exit = report
+            .and_then(|r| r.from_exit(state.cursor))
+            .map(|dt| format!(" (exit {:+.2}s)", dt))
+            .unwrap_or_default();
+        let value = value_at(t, v, state.cursor)
+            .map(|a| format!("{:.2} m/s2 ({:.2} g)", a, a / G))
+            .unwrap_or("-".to_string());
+        lines.push(Line::styled(
+            format!("cursor {:.2}s{}: {}", state.cursor, from_exit, value),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some(report) = file.reports.get(state.base_idx) {
+        lines.extend(report.to_string().lines().map(|l| Line::from(l.to_string())));
+    }
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title("summary")), area);
+}
+
+fn draw_chart(frame: &mut Frame, area: Rect, file: &SessionFile, session: &Session, state: &TuiState) {
+    let (t, v) = match file.acc_sma.get(state.base_idx) {
+        Some(series) => series,
+        None         => return,
+    };
+    let (a, b) = state.view_range(file);
+    let len = t.len().min(v.len());
+    let (i0, i1) = (t[..len].partition_point(|x| *x < a), t[..len].partition_point(|x| *x <= b));
+    let step = ((i1 - i0) / (area.width as usize * CHART_POINTS_PER_COL).max(1)).max(1);
+    let points: Vec<(f64, f64)> = (i0..i1).step_by(step).map(|i| (t[i], v[i])).collect();
+
+    let (y_min, y_max) = points.iter().fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+    let (y_min, y_max) = if y_min <= y_max { (y_min.min(0.0), y_max.max(G) * 1.05) } else { (0.0, 2.0 * G) };
+
+    let vertical = |time: f64| vec![(time, y_min), (time, y_max)];
+    let events = file.reports.get(state.base_idx).map(report_events).unwrap_or_default();
+    let event_lines: Vec<Vec<(f64, f64)>> = events.iter().map(|(_, time, _)| vertical(*time)).collect();
+    let cursor = vertical(state.cursor);
+
+    let mut datasets = vec![
+        Dataset::default()
+            .name(format!("|acc| sma {}", session.sma_bases[state.base_idx]))
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&points),
+    ];
+    for ((name, _, color), line) in events.iter().zip(event_lines.iter()) {
+        datasets.push(Dataset::default()
+            .name(*name)
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(*color))
+            .data(line));
+    }
+    datasets.push(Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::White))
+        .data(&cursor));
+
+    let chart = Chart::new(datasets)
+        .block(Block::bordered().title(format!("accel magnitude, {:.1}s - {:.1}s", a, b)))
+        .x_axis(Axis::default()
+            .bounds([a, b])
+            .labels([format!("{:.1}", a), format!("{:.1}", (a + b) / 2.0), format!("{:.1}", b)]))
+        .y_axis(Axis::default()
+            .bounds([y_min, y_max])
+            .labels([format!("{:.0}", y_min), format!("{:.0}", (y_min + y_max) / 2.0), format!("{:.0}", y_max)]));
+    frame.render_widget(chart, area);
+}
+
+fn draw_params(frame: &mut Frame, area: Rect, session: &Session, state: &TuiState) {
+    let bases = session.sma_bases
+        .iter()
+        .enumerate()
+        .map(|(i, b)| if i == state.base_idx { format!("[{}]", b) } else { b.to_string() })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut items = vec![ListItem::new(format!("sma bases: {}", bases))];
+    items.extend(session.params().iter().map(|p| ListItem::new(format!("{: <28} {:.3}", p.label(), p.value))));
+
+    let mut list_state = ListState::default().with_selected(match state.focus {
+        Focus::Params => Some(state.param_idx + 1),
+        Focus::Files  => None,
+    });
+    let list = List::new(items)
+        .block(Block::bordered().title("parameters").border_style(focus_style(state.focus == Focus::Params)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+fn focus_style(is_focused: bool) -> Style {
+    if is_focused { Style::default().fg(Color::Yellow) } else { Style::default() }
+}
+
+fn draw(frame: &mut Frame, session: &Session, state: &TuiState) {
+    let [top, chart_area, status_area] = Layout::vertical([
+        Constraint::Percentage(40),
+        Constraint::Min(10),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [left, summary_area] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(top);
+    let [files_area, params_area] = Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(left);
+
+    draw_files(frame, files_area, session, state);
+    draw_params(frame, params_area, session, state);
+    if let Some(file) = session.files.get(state.file_idx) {
+        draw_summary(frame, summary_area, file, state);
+        draw_chart(frame, chart_area, file, session, state);
+    }
+
+    let status = match &state.input {
+        Some((InputTarget::SmaBases, text))   => format!("sma bases> {}_", text),
+        Some((InputTarget::Param(idx), text)) => format!("{}> {}_",
+            session.params().get(*idx).map(|p| p.label()).unwrap_or_default(),
+            text,
+        ),
+        None => state.status.clone(),
+    };
+    frame.render_widget(Paragraph::new(status), status_area);
+}
+
+
+
+fn run_loop(terminal: &mut DefaultTerminal, session: &mut Session, plot: &dyn Fn(&SessionFile, &[usize])) -> io::Result<()> {
+    let mut state = TuiState::new(session);
+    loop {
+        terminal.draw(|frame| draw(frame, session, &state))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let is_plot = key.code == KeyCode::Char('g') && state.input.is_none();
+            if !state.handle_key(session, key, plot) {
+                return Ok(());
+            }
+            // gnuplot and the plot helpers write to the terminal
+            if is_plot {
+                terminal.clear()?;
+            }
+        }
+    }
+}
+
+/// Interactive view of the session files until 'q', `plot` draws the gnuplot charts of a file
+pub fn run_tui(session: &mut Session, plot: &dyn Fn(&SessionFile, &[usize])) -> io::Result<()> {
+    if session.files.is_empty() {
+        return Ok(());
+    }
+    let mut terminal = ratatui::try_init()?;
+    let res = run_loop(&mut terminal, session, plot);
+    ratatui::restore();
+    res
+}
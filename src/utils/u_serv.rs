@@ -1,22 +1,9 @@
 use std::f64;
 
+use serde::{Deserialize, Serialize};
 
 
-pub fn prompt_to_exit(msg: &str) {
-    println!("{}\nPress 'enter' to exit...\n", {msg});
-    let mut input = String::new();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-}
 
-pub fn prompt_to_continue(msg: &str) {
-    println!("{}\nPress 'enter' to continue...\n", {msg});
-    let mut input = String::new();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-}
 
 
 pub fn abs_max(f_prev: f64, f_new: f64) -> f64 {
@@ -35,7 +22,7 @@ pub fn ends_with_one(value: usize) -> bool {
 
 
 pub enum Axis3d { X, Y, Z }
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Vector3d {
     pub x: f64,
     pub y: f64,
@@ -73,6 +60,15 @@ impl Vector3d {
         f64::sqrt(self.x.powi(2) + self.y.powi(2) + self.z.powi(2))
     }
 
+    pub fn normalized(&self) -> Self {
+        let magnitude = self.magnitude();
+        if magnitude > 0.0 {
+            self.apply_for_all_axis(|val| val / magnitude)
+        } else {
+            self.clone()
+        }
+    }
+
     pub fn sum_axis(&self) -> f64 {
         self.x + self.y + self.z
     }
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use telemetry_parser::filesystem::gopro_chapter_paths;
+
+use ffmin_parser::jump_report::build_jump_report;
+use ffmin_parser::telemetry_parser_serv::get_result_metadata_for_file;
+
+
+
+pub const WATCH_FILE_EXTENSIONS: [&str; 4] = ["mp4", "insv", "360", "gcsv"];
+pub const WATCH_STATE_FILE     : &str = ".ffmin_watch_state.json";
+pub const REPORT_FILE_POSTFIX  : &str = ".report.json";
+pub const POLL_INTERVAL        : Duration = Duration::from_secs(2);
+/// polls with the same size and mtime before the file counts as copied
+pub const STABLE_POLLS         : usize = 3;
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size : u64,
+    pub mtime: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedFile {
+    pub stamp         : FileStamp,
+    pub processed_unix: u64,
+    pub report        : Option<String>,
+    pub error         : Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    pub processed: BTreeMap<String, ProcessedFile>,
+}
+
+impl WatchState {
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(state_str) => serde_json::from_str(&state_str).unwrap_or_else(|e| {
+                println!("INVALID WATCH STATE {:?}: {}, starting over", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let state_json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, state_json)
+    }
+
+    fn is_processed(&self, path: &str, stamp: &FileStamp) -> bool {
+        self.processed.get(path).map(|p| &p.stamp == stamp).unwrap_or(false)
+    }
+}
+
+
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn get_file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    Some(FileStamp { size: metadata.len(), mtime })
+}
+
+/// Video files under `dir`, card mounts keep them in `DCIM/xxxGOPRO` so the walk is recursive
+pub fn list_video_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return files,
+    };
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        if path.is_dir() {
+            files.extend(list_video_files(&path));
+        } else if path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| WATCH_FILE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            .unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+fn save_report(src_file: &str, dest_dir: &Path, sma_base: usize) -> Result<PathBuf, String> {
+    // WATCH_FILE_EXTENSIONS has no CSV, no mapping needed
+    let data = get_result_metadata_for_file(src_file, "")?;
+    let report = build_jump_report(&data, sma_base);
+    println!("{}", report);
+
+    let stem = Path::new(src_file).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let report_path = dest_dir.join(format!("{}{}", stem, REPORT_FILE_POSTFIX));
+    let report_json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    fs::write(&report_path, report_json).map_err(|e| e.to_string())?;
+    Ok(report_path)
+}
+
+
+
+/// Polls `src_dir` for new video files and writes a jump report per recording into `dest_dir`
+/// once every file of it has stopped growing. Runs a single pass if `once`
+pub fn watch_dir(src_dir: &str, dest_dir: &str, sma_base: usize, once: bool) {
+    let dest_dir = PathBuf::from(dest_dir);
+    if let Err(e) = fs::create_dir_all(&dest_dir) {
+        println!("ERR: failed to create {:?} {}", dest_dir, e);
+        return;
+    }
+    let state_path = dest_dir.join(WATCH_STATE_FILE);
+    let mut state = WatchState::load(&state_path);
+    // path -> (last stamp, polls without change)
+    let mut pending: BTreeMap<String, (FileStamp, usize)> = BTreeMap::new();
+
+    println!("WATCHING {} -> {:?}, {} files already processed", src_dir, dest_dir, state.processed.len());
+    loop {
+        let mut stable_files = Vec::new();
+        for path in list_video_files(Path::new(src_dir)) {
+            let path_str = path.to_string_lossy().to_string();
+            let stamp = match get_file_stamp(&path) {
+                Some(stamp) => stamp,
+                None => continue,
+            };
+            if state.is_processed(&path_str, &stamp) {
+                continue;
+            }
+            let entry = pending.entry(path_str.clone()).or_insert((stamp.clone(), 0));
+            if entry.0 == stamp {
+                entry.1 += 1;
+            } else {
+                *entry = (stamp, 0);
+            }
+            if entry.1 >= STABLE_POLLS || once {
+                stable_files.push(path_str);
+            }
+        }
+
+        for src_file in stable_files {
+            // already done with an earlier chapter of the same recording
+            if !pending.contains_key(&src_file) {
+                continue;
+            }
+            // a chapter waits for the rest of its recording
+            let chapters = gopro_chapter_paths(&src_file);
+            let is_copying = chapters.iter().any(|ch| {
+                !once && pending.get(ch).map(|(_, polls)| *polls < STABLE_POLLS).unwrap_or(false)
+            });
+            if is_copying {
+                continue;
+            }
+
+            println!("\nPROCESSING {}", chapters[0]);
+            let (report, error) = match save_report(&chapters[0], &dest_dir, sma_base) {
+                Ok(report_path) => (Some(report_path.to_string_lossy().to_string()), None),
+                Err(e)          => { println!("ERR: {}", e); (None, Some(e)) },
+            };
+            for chapter in &chapters {
+                let stamp = match get_file_stamp(Path::new(chapter)) {
+                    Some(stamp) => stamp,
+                    None => continue,
+                };
+                pending.remove(chapter);
+                state.processed.insert(chapter.clone(), ProcessedFile {
+                    stamp,
+                    processed_unix: unix_now(),
+                    report: report.clone(),
+                    error : error.clone(),
+                });
+            }
+            if let Err(e) = state.save(&state_path) {
+                println!("ERR: failed to save watch state {}", e);
+            }
+        }
+
+        if once {
+            break;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::io::*;
+use std::sync::{ Arc, atomic::{ AtomicBool, Ordering::Relaxed } };
+use std::path::Path;
+use byteorder::{ ReadBytesExt, LittleEndian };
+
+use crate::tags_impl::*;
+use crate::*;
+use crate::util::{ BARO_GROUP, GPS_EPOCH, GPS_LEAP_SECONDS };
+
+// ArduPilot DataFlash .bin logs: `A3 95 <type>` and a fixed size payload, the layout of every type
+// is described by a FMT message before it. Times are `TimeUS`, microseconds from the boot
+// https://ardupilot.org/dev/docs/code-overview-adding-a-new-log-message.html
+
+const HEAD: [u8; 2] = [0xA3, 0x95];
+const FMT_TYPE: u8 = 128;
+/// FMT: type, length, name[4], format[16], labels[64]
+const FMT_LENGTH: usize = 89;
+/// GPS status 3 and up is a 3D fix
+const GPS_3D_FIX: f64 = 3.0;
+
+#[derive(Default)]
+pub struct ArduPilot {
+    pub model: Option<String>,
+}
+
+#[derive(Clone)]
+struct Format {
+    name: String,
+    /// whole message, with the 3 header bytes
+    length: usize,
+    types: Vec<u8>,
+    labels: Vec<String>,
+    offsets: Vec<usize>,
+}
+
+fn type_size(t: u8) -> usize {
+    match t {
+        b'b' | b'B' | b'M' => 1,
+        b'h' | b'H' | b'c' | b'C' => 2,
+        b'i' | b'I' | b'f' | b'e' | b'E' | b'L' | b'n' => 4,
+        b'd' | b'q' | b'Q' => 8,
+        b'N' => 16,
+        b'Z' | b'a' => 64,
+        _ => 0
+    }
+}
+
+fn read_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+impl Format {
+    fn new(name: String, length: usize, types: &str, labels: &str) -> Self {
+        let types = types.as_bytes().to_vec();
+        let mut offsets = Vec::with_capacity(types.len());
+        let mut offset = 0;
+        for t in &types {
+            offsets.push(offset);
+            offset += type_size(*t);
+        }
+        Self { name, length, types, labels: labels.split(',').map(|x| x.to_owned()).collect(), offsets }
+    }
+
+    fn index(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|x| x == label)
+    }
+
+    /// Numeric field value in its unit: `c`, `C`, `e`, `E` are * 100, `L` is degrees * 1e7
+    fn get(&self, payload: &[u8], label: &str) -> Option<f64> {
+        let i = self.index(label)?;
+        let mut d = payload.get(*self.offsets.get(i)?..)?;
+        let v = match self.types[i] {
+            b'b' => d.read_i8().ok()? as f64,
+            b'B' | b'M' => d.read_u8().ok()? as f64,
+            b'h' => d.read_i16::<LittleEndian>().ok()? as f64,
+            b'H' => d.read_u16::<LittleEndian>().ok()? as f64,
+            b'i' => d.read_i32::<LittleEndian>().ok()? as f64,
+            b'I' => d.read_u32::<LittleEndian>().ok()? as f64,
+            b'f' => d.read_f32::<LittleEndian>().ok()? as f64,
+            b'd' => d.read_f64::<LittleEndian>().ok()?,
+            b'q' => d.read_i64::<LittleEndian>().ok()? as f64,
+            b'Q' => d.read_u64::<LittleEndian>().ok()? as f64,
+            b'c' => d.read_i16::<LittleEndian>().ok()? as f64 / 100.0,
+            b'C' => d.read_u16::<LittleEndian>().ok()? as f64 / 100.0,
+            b'e' => d.read_i32::<LittleEndian>().ok()? as f64 / 100.0,
+            b'E' => d.read_u32::<LittleEndian>().ok()? as f64 / 100.0,
+            b'L' => d.read_i32::<LittleEndian>().ok()? as f64 / 10_000_000.0,
+            _ => return None
+        };
+        Some(v)
+    }
+
+    fn get_str(&self, payload: &[u8], label: &str) -> Option<String> {
+        let i = self.index(label)?;
+        let start = *self.offsets.get(i)?;
+        Some(read_str(payload.get(start..start + type_size(self.types[i]))?))
+    }
+}
+
+/// Roll, pitch, yaw in degrees, ZYX order
+fn euler_to_quat(roll: f64, pitch: f64, yaw: f64) -> Quaternion<f64> {
+    let (sr, cr) = (roll.to_radians() / 2.0).sin_cos();
+    let (sp, cp) = (pitch.to_radians() / 2.0).sin_cos();
+    let (sy, cy) = (yaw.to_radians() / 2.0).sin_cos();
+    Quaternion {
+        w: cr * cp * cy + sr * sp * sy,
+        x: sr * cp * cy - cr * sp * sy,
+        y: cr * sp * cy + sr * cp * sy,
+        z: cr * cp * sy - sr * sp * cy,
+    }
+}
+
+impl ArduPilot {
+    pub fn camera_type(&self) -> String {
+        "ArduPilot".into()
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        false
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["bin"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], _filepath: P) -> Option<Self> {
+        // the log starts with the FMT of FMT
+        if buffer.len() >= 4 && buffer[0..2] == HEAD && buffer[2] == FMT_TYPE && buffer[3] == FMT_TYPE {
+            Some(Self::default())
+        } else {
+            None
+        }
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, size: usize, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut bytes = Vec::with_capacity(size);
+        stream.read_to_end(&mut bytes)?;
+
+        let mut formats: Vec<Option<Format>> = vec![None; 256];
+        formats[FMT_TYPE as usize] = Some(Format::new("FMT".into(), FMT_LENGTH, "BBnNZ", "Type,Length,Name,Format,Columns"));
+
+        let mut gyro = Vec::new();
+        let mut accl = Vec::new();
+        let mut quat = Vec::new();
+        let mut gps = Vec::new();
+        let mut baro = Vec::new();
+
+        let mut pos = 0;
+        while pos + 3 <= bytes.len() {
+            if bytes[pos..pos + 2] != HEAD {
+                pos += 1; // corrupted block, find the next message
+                continue;
+            }
+            let fmt = match &formats[bytes[pos + 2] as usize] {
+                Some(fmt) if pos + fmt.length <= bytes.len() => fmt,
+                _ => { pos += 1; continue; }
+            };
+            let payload = &bytes[pos + 3..pos + fmt.length];
+            pos += fmt.length;
+
+            if pos % (1024 * 1024) < fmt.length {
+                if cancel_flag.load(Relaxed) { break; }
+                progress_cb(pos as f64 / bytes.len() as f64);
+            }
+
+            let time = || fmt.get(payload, "TimeUS").map(|us| us / 1_000_000.0);
+            // only the first instance of multi-instance sensors
+            let first_instance = fmt.get(payload, "I").map(|i| i == 0.0).unwrap_or(true);
+
+            let mut new_format = None;
+            match fmt.name.as_str() {
+                "FMT" => {
+                    new_format = crate::try_block!((usize, Format), {
+                        let length = fmt.get(payload, "Length")? as usize;
+                        if length < 3 { return None; }
+                        (fmt.get(payload, "Type")? as usize, Format::new(fmt.get_str(payload, "Name")?, length, &fmt.get_str(payload, "Format")?, &fmt.get_str(payload, "Columns")?))
+                    });
+                },
+                "MSG" if self.model.is_none() => {
+                    // "ArduCopter V4.4.0 (ab6ffd43)"
+                    if let Some(msg) = fmt.get_str(payload, "Message").filter(|m| m.starts_with("Ardu")) {
+                        self.model = Some(msg);
+                    }
+                },
+                "IMU" if first_instance => {
+                    crate::try_block!({
+                        let t = time()?;
+                        gyro.push(TimeVector3 { t, x: fmt.get(payload, "GyrX")?, y: fmt.get(payload, "GyrY")?, z: fmt.get(payload, "GyrZ")? });
+                        accl.push(TimeVector3 { t, x: fmt.get(payload, "AccX")?, y: fmt.get(payload, "AccY")?, z: fmt.get(payload, "AccZ")? });
+                    });
+                },
+                "ATT" => {
+                    crate::try_block!({
+                        quat.push(TimeQuaternion { t: time()?, v: euler_to_quat(fmt.get(payload, "Roll")?, fmt.get(payload, "Pitch")?, fmt.get(payload, "Yaw")?) });
+                    });
+                },
+                "GPS" if first_instance => {
+                    crate::try_block!({
+                        let week_ms = fmt.get(payload, "GMS")?;
+                        let week = fmt.get(payload, "GWk")?;
+                        if week == 0.0 { return None; } // no time before the first fix
+                        gps.push((time()?, GpsData {
+                            is_acquired: fmt.get(payload, "Status")? >= GPS_3D_FIX,
+                            unix_timestamp: GPS_EPOCH + week * 604800.0 + week_ms / 1000.0 - GPS_LEAP_SECONDS,
+                            lat: fmt.get(payload, "Lat")?,
+                            lon: fmt.get(payload, "Lng")?,
+                            altitude: fmt.get(payload, "Alt")?,
+                            speed: fmt.get(payload, "Spd")? * 3.6, // m/s to km/h
+                            track: fmt.get(payload, "GCrs")?,
+                        }));
+                    });
+                },
+                "BARO" if first_instance => {
+                    crate::try_block!({
+                        baro.push(TimeScalar { t: time()?, v: fmt.get(payload, "Press")? });
+                    });
+                },
+                _ => { }
+            }
+            if let Some((typ, format)) = new_format {
+                formats[typ] = Some(format);
+            }
+        }
+
+        // zero is the first IMU reading
+        let start = gyro.first().map(|v: &TimeVector3<f64>| v.t)
+            .or(gps.first().map(|v| v.0))
+            .unwrap_or_default();
+        gyro.iter_mut().chain(accl.iter_mut()).for_each(|v| v.t -= start);
+        baro.iter_mut().for_each(|v| v.t -= start);
+        quat.iter_mut().for_each(|v| v.t = (v.t - start) * 1000.0);
+
+        let mut samples = Vec::new();
+
+        let mut map = GroupedTagMap::new();
+        if !gyro.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope,     TagId::Data, "Gyroscope data",     Vec_TimeVector3_f64, |v| format!("{:?}", v), gyro, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope,     TagId::Unit, "Gyroscope unit",     String, |v| v.to_string(), "rad/s".into(), Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Data, "Accelerometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), accl, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Unit, "Accelerometer unit", String, |v| v.to_string(), "m/s²".into(), Vec::new()));
+        }
+        if !quat.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Quaternion, TagId::Data, "Quaternion data", Vec_TimeQuaternion_f64, |v| format!("{:?}", v), quat, Vec::new()));
+        }
+        if !baro.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(BARO_GROUP.into()), TagId::Data, "Pressure data", Vec_TimeScalar_f64, |v| format!("{:?}", v), baro, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(BARO_GROUP.into()), TagId::Unit, "Pressure unit", String, |v| v.to_string(), "Pa".into(), Vec::new()));
+        }
+        if !map.is_empty() {
+            samples.push(SampleInfo { timestamp_ms: 0.0, tag_map: Some(map), ..Default::default() });
+        }
+
+        if let (Some(first), Some(last)) = (gps.first(), gps.last()) {
+            let timestamp_ms = (first.0 - start) * 1000.0;
+            let duration_ms = (last.1.unix_timestamp - first.1.unix_timestamp) * 1000.0;
+            let gps = gps.into_iter().map(|v| v.1).collect::<Vec<GpsData>>();
+
+            let mut map = GroupedTagMap::new();
+            util::insert_tag(&mut map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), gps, Vec::new()));
+            samples.push(SampleInfo { timestamp_ms, duration_ms, tag_map: Some(map), ..Default::default() });
+        }
+
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded(s: &str, len: usize) -> Vec<u8> {
+        let mut v = s.as_bytes().to_vec();
+        v.resize(len, 0);
+        v
+    }
+
+    fn message(typ: u8, payload: &[u8]) -> Vec<u8> {
+        let mut v = vec![HEAD[0], HEAD[1], typ];
+        v.extend(payload);
+        v
+    }
+
+    fn fmt(typ: u8, name: &str, format: &str, columns: &str) -> Vec<u8> {
+        let length = 3 + format.bytes().map(type_size).sum::<usize>();
+        let mut payload = vec![typ, length as u8];
+        payload.extend(padded(name, 4));
+        payload.extend(padded(format, 16));
+        payload.extend(padded(columns, 64));
+        message(FMT_TYPE, &payload)
+    }
+
+    fn imu(time_us: u64, instance: u8, gyro: [f32; 3], accl: [f32; 3]) -> Vec<u8> {
+        let mut payload = time_us.to_le_bytes().to_vec();
+        payload.push(instance);
+        payload.extend(gyro.iter().chain(&accl).flat_map(|x| x.to_le_bytes()));
+        message(129, &payload)
+    }
+
+    fn log() -> Vec<u8> {
+        let mut log = fmt(FMT_TYPE, "FMT", "BBnNZ", "Type,Length,Name,Format,Columns");
+        log.extend(fmt(129, "IMU", "QBffffff", "TimeUS,I,GyrX,GyrY,GyrZ,AccX,AccY,AccZ"));
+        log.extend(fmt(130, "ATT", "QccccCC", "TimeUS,DesRoll,Roll,DesPitch,Pitch,DesYaw,Yaw"));
+        log.extend(fmt(131, "GPS", "QBBIHBcLLeffffB", "TimeUS,I,Status,GMS,GWk,NSats,HDop,Lat,Lng,Alt,Spd,GCrs,VZ,Yaw,U"));
+        log.extend(fmt(132, "BARO", "QBff", "TimeUS,I,Alt,Press"));
+        log.extend(fmt(133, "MSG", "QZ", "TimeUS,Message"));
+
+        let mut msg = 1_000_000u64.to_le_bytes().to_vec();
+        msg.extend(padded("ArduCopter V4.4.0 (ab6ffd43)", 64));
+        log.extend(message(133, &msg));
+
+        log.extend(imu(2_000_000, 0, [0.1, 0.2, 0.3], [0.0, 0.0, -9.81]));
+        // the second IMU is skipped
+        log.extend(imu(2_000_000, 1, [5.0, 5.0, 5.0], [5.0, 5.0, 5.0]));
+
+        // roll 90°, centidegrees
+        let mut att = 2_500_000u64.to_le_bytes().to_vec();
+        att.extend([0i16, 9000, 0, 0].iter().flat_map(|x| x.to_le_bytes()));
+        att.extend([0u16, 0].iter().flat_map(|x| x.to_le_bytes()));
+        log.extend(message(130, &att));
+
+        // a corrupted byte between the messages
+        log.push(0x55);
+        log.extend(imu(2_010_000, 0, [0.4, 0.5, 0.6], [0.0, 0.0, -9.8]));
+
+        let mut baro = 2_020_000u64.to_le_bytes().to_vec();
+        baro.push(0);
+        baro.extend([120.0f32, 95000.0].iter().flat_map(|x| x.to_le_bytes()));
+        log.extend(message(132, &baro));
+
+        let mut gps = 3_000_000u64.to_le_bytes().to_vec();
+        gps.extend([0u8, 3]); // instance, 3D fix
+        gps.extend(302_418_000u32.to_le_bytes());
+        gps.extend(2262u16.to_le_bytes());
+        gps.push(12);
+        gps.extend(80i16.to_le_bytes());
+        gps.extend(465_000_000i32.to_le_bytes());
+        gps.extend(66_000_000i32.to_le_bytes());
+        gps.extend(100_050i32.to_le_bytes()); // cm
+        gps.extend([10.0f32, 45.0, 0.0, 0.0].iter().flat_map(|x| x.to_le_bytes()));
+        gps.push(1);
+        log.extend(message(131, &gps));
+        log
+    }
+
+    #[test]
+    fn reads_the_self_described_messages() {
+        let log = log();
+        let mut ardupilot = ArduPilot::detect(&log, "log.bin").unwrap();
+        let samples = ardupilot.parse(&mut Cursor::new(&log), log.len(), |_| (), Arc::new(AtomicBool::new(false))).unwrap();
+        assert_eq!(ardupilot.model.as_deref(), Some("ArduCopter V4.4.0 (ab6ffd43)"));
+        assert_eq!(samples.len(), 2);
+
+        let map = samples[0].tag_map.as_ref().unwrap();
+        let gyro = (map.get(&GroupId::Gyroscope).unwrap().get_t(TagId::Data) as Option<&Vec<TimeVector3<f64>>>).unwrap();
+        // IMU times are seconds from the first IMU reading
+        assert_eq!(gyro.iter().map(|v| (v.t * 1e6).round()).collect::<Vec<_>>(), [0.0, 10_000.0]);
+        assert!((gyro[1].z - 0.6).abs() < 1e-6);
+        let accl = (map.get(&GroupId::Accelerometer).unwrap().get_t(TagId::Data) as Option<&Vec<TimeVector3<f64>>>).unwrap();
+        assert!((accl[0].z + 9.81).abs() < 1e-6);
+
+        // quaternion times are milliseconds
+        let quat = (map.get(&GroupId::Quaternion).unwrap().get_t(TagId::Data) as Option<&Vec<TimeQuaternion<f64>>>).unwrap();
+        assert_eq!(quat.len(), 1);
+        assert!((quat[0].t - 500.0).abs() < 1e-6);
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((quat[0].v.w - half).abs() < 1e-6 && (quat[0].v.x - half).abs() < 1e-6);
+
+        let baro = (map.get(&GroupId::Custom(BARO_GROUP.into())).unwrap().get_t(TagId::Data) as Option<&Vec<TimeScalar<f64>>>).unwrap();
+        assert!((baro[0].t - 0.02).abs() < 1e-9);
+        assert_eq!(baro[0].v, 95000.0);
+
+        assert!((samples[1].timestamp_ms - 1000.0).abs() < 1e-6);
+        let gps = (samples[1].tag_map.as_ref().unwrap().get(&GroupId::GPS).unwrap().get_t(TagId::Data) as Option<&Vec<GpsData>>).unwrap();
+        assert_eq!(gps.len(), 1);
+        assert!(gps[0].is_acquired);
+        assert_eq!(gps[0].unix_timestamp, GPS_EPOCH + 2262.0 * 604800.0 + 302_418.0 - GPS_LEAP_SECONDS);
+        assert_eq!((gps[0].lat, gps[0].lon, gps[0].altitude), (46.5, 6.6, 1000.5));
+        assert_eq!((gps[0].speed, gps[0].track), (36.0, 45.0));
+    }
+
+    #[test]
+    fn detects_only_the_fmt_of_fmt() {
+        assert!(ArduPilot::detect(&log(), "log.bin").is_some());
+        assert!(ArduPilot::detect(&message(129, &[0; 8]), "log.bin").is_none());
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::collections::BTreeMap;
+use std::io::*;
+use std::sync::{ Arc, atomic::{ AtomicBool, Ordering::Relaxed } };
+use std::path::Path;
+
+use crate::tags_impl::*;
+use crate::*;
+use fc_blackbox::{ BlackboxReader, BlackboxRecord };
+use memchr::memmem;
+
+// Betaflight / INAV / Cleanflight blackbox logs. One file can have several logs (one per arming),
+// every one of them starts with the product header
+
+const LOG_START: &[u8] = b"H Product:Blackbox flight data recorder by Nicholas Sherlock";
+
+/// `Firmware revision` names of the firmwares with Cleanflight's `gyro_scale`
+const CLEANFLIGHT_FORKS: [&str; 4] = ["Cleanflight", "Betaflight", "INAV", "EmuFlight"];
+
+/// (gyro, accelerometer)
+type ImuLog = (Vec<TimeVector3<f64>>, Vec<TimeVector3<f64>>);
+
+#[derive(Default)]
+pub struct Blackbox {
+    pub model: Option<String>,
+    firmware: Option<String>,
+}
+
+impl Blackbox {
+    pub fn camera_type(&self) -> String {
+        self.firmware.clone().unwrap_or_else(|| "Blackbox".into())
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        false
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["bbl", "bfl", "txt"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], _filepath: P) -> Option<Self> {
+        memmem::find(&buffer[..buffer.len().min(1024)], LOG_START)?;
+        let header = parse_header(buffer);
+        // "Betaflight 4.4.2 (025ab5f9f) STM32F7X2"
+        let revision = header.get("Firmware revision");
+        let firmware = revision.and_then(|r| r.split_whitespace().next()).map(|x| x.to_owned())
+            .or(header.get("Firmware type").cloned());
+        let model = header.get("Craft name").filter(|x| !x.is_empty()).cloned().or(revision.cloned());
+        Some(Self { model, firmware })
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, size: usize, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut bytes = Vec::with_capacity(size);
+        stream.read_to_end(&mut bytes)?;
+
+        let mut starts = memmem::find_iter(&bytes, LOG_START).collect::<Vec<usize>>();
+        starts.push(bytes.len());
+
+        let mut samples = Vec::new();
+        // logs of one power cycle share the clock, a log from another one is moved after the previous
+        let mut last_time = None;
+        for (index, log) in starts.windows(2).enumerate() {
+            if cancel_flag.load(Relaxed) { break; }
+            let log_bytes = &bytes[log[0]..log[1]];
+            match parse_log(log_bytes, last_time) {
+                Ok((gyro, accl)) => {
+                    if let Some(last) = gyro.last().or(accl.last()).map(|v| v.t) {
+                        let first = gyro.first().or(accl.first()).map(|v| v.t).unwrap_or_default();
+                        last_time = Some(last);
+
+                        let mut map = GroupedTagMap::new();
+                        if !gyro.is_empty() {
+                            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Data, "Gyroscope data", Vec_TimeVector3_f64, |v| format!("{:?}", v), gyro, Vec::new()));
+                            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Unit, "Gyroscope unit", String, |v| v.to_string(), "deg/s".into(), Vec::new()));
+                        }
+                        if !accl.is_empty() {
+                            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Data, "Accelerometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), accl, Vec::new()));
+                            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Unit, "Accelerometer unit", String, |v| v.to_string(), "g".into(), Vec::new()));
+                        }
+                        samples.push(SampleInfo { sample_index: index as u64, timestamp_ms: first * 1000.0, duration_ms: (last - first) * 1000.0, tag_map: Some(map), ..Default::default() });
+                    }
+                },
+                Err(e) => log::warn!("Blackbox log {} skipped: {}", index + 1, e)
+            }
+            progress_cb(log[1] as f64 / bytes.len().max(1) as f64);
+        }
+        if samples.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "No gyro or accelerometer data in the blackbox logs"));
+        }
+        Ok(samples)
+    }
+}
+
+/// "H name:value" lines at the start of the log
+fn parse_header(log: &[u8]) -> BTreeMap<String, String> {
+    let mut header = BTreeMap::new();
+    for line in log.split(|x| *x == b'\n') {
+        let line = match line.strip_prefix(b"H ") {
+            Some(line) => String::from_utf8_lossy(line),
+            None => break
+        };
+        if let Some((name, value)) = line.trim_end().split_once(':') {
+            header.insert(name.to_owned(), value.to_owned());
+        }
+    }
+    header
+}
+
+fn header_number(header: &BTreeMap<String, String>, name: &str) -> Option<f64> {
+    let v = header.get(name)?;
+    // floats are written as IEEE 754 bits in hex
+    match v.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().map(|bits| f32::from_bits(bits) as f64),
+        None => v.parse::<f64>().ok()
+    }
+}
+
+/// Gyro deg/s of one logged unit. As in blackbox-tools, Cleanflight and its forks (Betaflight, INAV) write
+/// a `gyro_scale` to deg/s, Baseflight (and the logs without the firmware type) one to rad/µs
+fn gyro_deg_per_s(header: &BTreeMap<String, String>) -> f64 {
+    let scale = match header_number(header, "gyro_scale").or_else(|| header_number(header, "gyro.scale")) {
+        Some(scale) => scale,
+        None => return 1.0
+    };
+    let is_cleanflight = header.get("Firmware type").is_some_and(|x| x == "Cleanflight")
+        || header.get("Firmware revision").and_then(|r| r.split_whitespace().next())
+            .is_some_and(|x| CLEANFLIGHT_FORKS.iter().any(|f| f.eq_ignore_ascii_case(x)));
+    if is_cleanflight {
+        scale
+    } else {
+        scale * 1_000_000.0 * 180.0 / std::f64::consts::PI
+    }
+}
+
+/// Gyro in deg/s and accelerometer in g, time in seconds of the flight controller clock
+fn parse_log(log: &[u8], after: Option<f64>) -> std::result::Result<ImuLog, String> {
+    let header = parse_header(log);
+    let fields = header.get("Field I name").ok_or("no main frame fields")?.split(',').map(|x| x.to_owned()).collect::<Vec<String>>();
+    let column = |name: &str| fields.iter().position(|x| x == name);
+    let xyz = |prefix: &str| -> Option<[usize; 3]> {
+        Some([column(&format!("{}[0]", prefix))?, column(&format!("{}[1]", prefix))?, column(&format!("{}[2]", prefix))?])
+    };
+
+    let time = column("time").ok_or("no time field")?;
+    let gyro_cols = xyz("gyroADC").or_else(|| xyz("gyroUnfilt"));
+    let accl_cols = xyz("accSmooth");
+
+    let gyro_scale = gyro_deg_per_s(&header);
+    let acc_1g = header_number(&header, "acc_1g").filter(|v| *v > 0.0).unwrap_or(1.0);
+
+    let mut bbox = BlackboxReader::from_bytes(log).map_err(|e| e.to_string())?;
+
+    let mut gyro = Vec::new();
+    let mut accl = Vec::new();
+    let mut offset = None;
+    while let Some(record) = bbox.next() {
+        if let BlackboxRecord::Main(values) = record {
+            let t = match values.get(time) {
+                Some(us) => *us as f64 / 1_000_000.0,
+                None => continue
+            };
+            let offset = *offset.get_or_insert(match after {
+                Some(after) if t <= after => after - t + 0.001,
+                _ => 0.0
+            });
+            let read = |cols: [usize; 3], scale: f64| -> Option<TimeVector3<f64>> {
+                Some(TimeVector3 { t: t + offset, x: *values.get(cols[0])? as f64 * scale, y: *values.get(cols[1])? as f64 * scale, z: *values.get(cols[2])? as f64 * scale })
+            };
+            if let Some(v) = gyro_cols.and_then(|c| read(c, gyro_scale)) { gyro.push(v); }
+            if let Some(v) = accl_cols.and_then(|c| read(c, 1.0 / acc_1g)) { accl.push(v); }
+        }
+    }
+    Ok((gyro, accl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsigned_vb(mut v: u32, out: &mut Vec<u8>) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+    fn signed_vb(v: i32, out: &mut Vec<u8>) {
+        unsigned_vb(((v << 1) ^ (v >> 31)) as u32, out);
+    }
+
+    /// Log with `firmware` header lines and one I-frame at 1 s
+    fn log(firmware: &str, gyro_scale: f32, gyro: [i32; 3]) -> Vec<u8> {
+        let mut out = format!("{}\n\
+            H Data version:2\n\
+            H I interval:1\n\
+            H P interval:1/1\n\
+            {}\
+            H gyro_scale:0x{:08x}\n\
+            H Field I name:loopIteration,time,gyroADC[0],gyroADC[1],gyroADC[2]\n\
+            H Field I signed:0,0,1,1,1\n\
+            H Field I predictor:0,0,0,0,0\n\
+            H Field I encoding:1,1,0,0,0\n\
+            H Field P predictor:6,2,1,1,1\n\
+            H Field P encoding:9,0,0,0,0\n",
+            std::str::from_utf8(LOG_START).unwrap(), firmware, gyro_scale.to_bits()).into_bytes();
+        out.push(b'I');
+        unsigned_vb(0, &mut out);
+        unsigned_vb(1_000_000, &mut out);
+        for v in gyro {
+            signed_vb(v, &mut out);
+        }
+        out
+    }
+
+    fn gyro_of(log: &[u8]) -> TimeVector3<f64> {
+        let (gyro, accl) = parse_log(log, None).unwrap();
+        assert!(accl.is_empty());
+        assert_eq!(gyro.len(), 1);
+        assert_eq!(gyro[0].t, 1.0);
+        gyro[0].clone()
+    }
+
+    fn assert_deg_per_s(v: TimeVector3<f64>, expected: [f64; 3]) {
+        for (v, e) in [v.x, v.y, v.z].into_iter().zip(expected) {
+            assert!((v - e).abs() < 0.001, "{} != {}", v, e);
+        }
+    }
+
+    #[test]
+    fn betaflight_gyro_is_deg_per_s() {
+        let firmware = "H Firmware type:Cleanflight\nH Firmware revision:Betaflight 4.4.2 (025ab5f9f) STM32F7X2\n";
+        assert_deg_per_s(gyro_of(&log(firmware, 1.0, [100, -200, 300])), [100.0, -200.0, 300.0]);
+    }
+
+    #[test]
+    fn inav_gyro_is_scaled_to_deg_per_s() {
+        let firmware = "H Firmware type:Cleanflight\nH Firmware revision:INAV 7.1.0 (a1b2c3d4) MATEKF405\n";
+        // raw MPU6000 units at 2000 deg/s
+        assert_deg_per_s(gyro_of(&log(firmware, 1.0 / 16.4, [164, -328, 0])), [10.0, -20.0, 0.0]);
+    }
+
+    #[test]
+    fn baseflight_gyro_scale_is_rad_per_us() {
+        let firmware = "H Firmware type:Baseflight\n";
+        let scale = (4.0 / 16.4 * std::f64::consts::PI / 180.0 * 0.000_001) as f32;
+        assert_deg_per_s(gyro_of(&log(firmware, scale, [41, 0, -82])), [10.0, 0.0, -20.0]);
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::io::*;
+use std::sync::{ Arc, atomic::AtomicBool };
+use std::path::Path;
+use byteorder::{ ReadBytesExt, LittleEndian };
+
+use crate::tags_impl::*;
+use crate::*;
+use crate::util::{ GPS_EPOCH, GPS_LEAP_SECONDS };
+use memchr::memmem;
+
+// Camera Motion Metadata: a metadata track with the `camm` sample entry, one little endian packet per sample
+// https://developers.google.com/streetview/publish/camm-spec
+
+#[derive(Default)]
+pub struct Camm {
+    pub model: Option<String>,
+}
+
+/// Payload size of the packet types 0-7, a sample of any other size isn't a CAMM packet
+const PACKET_SIZES: [usize; 8] = [12, 8, 12, 12, 12, 24, 56, 12];
+
+#[derive(Default)]
+struct Packets {
+    gyro: Vec<TimeVector3<f64>>,
+    accl: Vec<TimeVector3<f64>>,
+    magn: Vec<TimeVector3<f64>>,
+    quat: Vec<TimeQuaternion<f64>>,
+    gps: Vec<GpsData>,
+    /// track time of the first and the last GPS packet, ms
+    gps_times: Option<(f64, f64)>,
+}
+
+impl Camm {
+    pub fn camera_type(&self) -> String {
+        "CAMM".into()
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        true
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["mp4", "mov"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], _filepath: P) -> Option<Self> {
+        // stsd: version and flags, entry count, then the first entry size and type
+        memmem::find_iter(buffer, b"stsd")
+            .any(|pos| buffer.get(pos + 16..pos + 20) == Some(b"camm"))
+            .then(Self::default)
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, size: usize, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut packets = Packets::default();
+
+        util::get_metadata_track_samples(stream, size, false, |info: SampleInfo, data: &[u8], file_position: u64, _video_md: Option<&VideoMetadata>| {
+            if size > 0 {
+                progress_cb(file_position as f64 / size as f64);
+            }
+            if let Err(e) = read_packet(data, info.timestamp_ms, &mut packets) {
+                log::debug!("Not a CAMM packet at {}: {}", file_position, e);
+            }
+        }, cancel_flag)?;
+
+        let mut samples = Vec::new();
+
+        let mut map = GroupedTagMap::new();
+        if !packets.gyro.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Data, "Gyroscope data", Vec_TimeVector3_f64, |v| format!("{:?}", v), packets.gyro, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Unit, "Gyroscope unit", String, |v| v.to_string(), "rad/s".into(), Vec::new()));
+        }
+        if !packets.accl.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Data, "Accelerometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), packets.accl, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Unit, "Accelerometer unit", String, |v| v.to_string(), "m/s²".into(), Vec::new()));
+        }
+        if !packets.magn.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Magnetometer, TagId::Data, "Magnetometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), packets.magn, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Magnetometer, TagId::Unit, "Magnetometer unit", String, |v| v.to_string(), "μT".into(), Vec::new()));
+        }
+        if !packets.quat.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Quaternion, TagId::Data, "Quaternion data", Vec_TimeQuaternion_f64, |v| format!("{:?}", v), packets.quat, Vec::new()));
+        }
+        if !map.is_empty() {
+            samples.push(SampleInfo { timestamp_ms: 0.0, tag_map: Some(map), ..Default::default() });
+        }
+
+        if let Some((gps_start, gps_end)) = packets.gps_times {
+            let duration_ms = gps_end - gps_start;
+            let mut map = GroupedTagMap::new();
+            util::insert_tag(&mut map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), packets.gps, Vec::new()));
+            samples.push(SampleInfo { timestamp_ms: gps_start, duration_ms, tag_map: Some(map), ..Default::default() });
+        }
+
+        if samples.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "No CAMM packets"));
+        }
+        Ok(samples)
+    }
+}
+
+fn read_vector(d: &mut &[u8], t: f64) -> Result<TimeVector3<f64>> {
+    Ok(TimeVector3 {
+        t,
+        x: d.read_f32::<LittleEndian>()? as f64,
+        y: d.read_f32::<LittleEndian>()? as f64,
+        z: d.read_f32::<LittleEndian>()? as f64,
+    })
+}
+
+/// Angle-axis: the direction is the axis, the length is the angle in radians
+fn angle_axis_to_quat(v: &TimeVector3<f64>) -> Quaternion<f64> {
+    let angle = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    if angle < f64::EPSILON {
+        return Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+    }
+    let s = (angle / 2.0).sin() / angle;
+    Quaternion { w: (angle / 2.0).cos(), x: v.x * s, y: v.y * s, z: v.z * s }
+}
+
+fn read_packet(data: &[u8], timestamp_ms: f64, packets: &mut Packets) -> Result<()> {
+    let mut d = data;
+    let reserved = d.read_u16::<LittleEndian>()?;
+    let packet_type = d.read_u16::<LittleEndian>()? as usize;
+    if reserved != 0 || packet_type >= PACKET_SIZES.len() || d.len() != PACKET_SIZES[packet_type] {
+        return Err(Error::new(ErrorKind::InvalidData, format!("reserved {}, type {}, size {}", reserved, packet_type, data.len())));
+    }
+    let t = timestamp_ms / 1000.0;
+    match packet_type {
+        0 => {
+            let v = read_vector(&mut d, t)?;
+            packets.quat.push(TimeQuaternion { t: timestamp_ms, v: angle_axis_to_quat(&v) });
+        },
+        2 => packets.gyro.push(read_vector(&mut d, t)?),
+        3 => packets.accl.push(read_vector(&mut d, t)?),
+        7 => packets.magn.push(read_vector(&mut d, t)?),
+        5 => {
+            // minimal GPS has no time, only the track time of the sample
+            packets.gps.push(GpsData {
+                is_acquired: true,
+                unix_timestamp: 0.0,
+                lat: d.read_f64::<LittleEndian>()?,
+                lon: d.read_f64::<LittleEndian>()?,
+                altitude: d.read_f64::<LittleEndian>()?,
+                speed: 0.0,
+                track: 0.0,
+            });
+            packets.gps_times = Some((packets.gps_times.map_or(timestamp_ms, |x| x.0), timestamp_ms));
+        },
+        6 => {
+            let time_gps_epoch = d.read_f64::<LittleEndian>()?;
+            let fix_type = d.read_i32::<LittleEndian>()?;
+            let lat = d.read_f64::<LittleEndian>()?;
+            let lon = d.read_f64::<LittleEndian>()?;
+            let altitude = d.read_f32::<LittleEndian>()? as f64;
+            let _horizontal_accuracy = d.read_f32::<LittleEndian>()?;
+            let _vertical_accuracy = d.read_f32::<LittleEndian>()?;
+            let vel_east = d.read_f32::<LittleEndian>()? as f64;
+            let vel_north = d.read_f32::<LittleEndian>()? as f64;
+            packets.gps.push(GpsData {
+                is_acquired: fix_type >= 2,
+                unix_timestamp: GPS_EPOCH + time_gps_epoch - GPS_LEAP_SECONDS,
+                lat,
+                lon,
+                altitude,
+                speed: (vel_east * vel_east + vel_north * vel_north).sqrt() * 3.6, // m/s to km/h
+                track: (vel_east.atan2(vel_north).to_degrees() + 360.0) % 360.0,
+            });
+            packets.gps_times = Some((packets.gps_times.map_or(timestamp_ms, |x| x.0), timestamp_ms));
+        },
+        // exposure and position aren't used
+        _ => { }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(packet_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut data = 0u16.to_le_bytes().to_vec();
+        data.extend(packet_type.to_le_bytes());
+        data.extend(payload);
+        data
+    }
+    fn f32s(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+    fn f64s(values: &[f64]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn reads_every_packet_type() {
+        let mut full_gps = f64s(&[1_370_000_000.5]);
+        full_gps.extend(3i32.to_le_bytes());
+        full_gps.extend(f64s(&[46.5, 6.6]));
+        // altitude, accuracies, east and north velocity, up velocity, speed accuracy
+        full_gps.extend(f32s(&[1000.5, 2.0, 3.0, 3.0, 4.0, 0.5, 0.1]));
+
+        let mut packets = Packets::default();
+        let samples = [
+            (0, packet(0, &f32s(&[std::f32::consts::PI, 0.0, 0.0]))),
+            (10, packet(1, &f64s(&[0.01]))),
+            (20, packet(2, &f32s(&[0.1, 0.2, 0.3]))),
+            (30, packet(3, &f32s(&[0.0, 0.0, 9.81]))),
+            (40, packet(4, &f32s(&[1.0, 2.0, 3.0]))),
+            (50, packet(5, &f64s(&[46.4, 6.5, 990.0]))),
+            (1050, packet(6, &full_gps)),
+            (1060, packet(7, &f32s(&[20.0, -5.0, 40.0]))),
+        ];
+        for (timestamp_ms, data) in &samples {
+            read_packet(data, *timestamp_ms as f64, &mut packets).unwrap();
+        }
+
+        // a half turn around x
+        assert_eq!(packets.quat.len(), 1);
+        assert_eq!(packets.quat[0].t, 0.0);
+        assert!(packets.quat[0].v.w.abs() < 1e-6 && (packets.quat[0].v.x - 1.0).abs() < 1e-6);
+
+        assert_eq!(packets.gyro.len(), 1);
+        assert_eq!(packets.gyro[0].t, 0.02);
+        assert!((packets.gyro[0].z - 0.3).abs() < 1e-6);
+        assert_eq!(packets.accl.len(), 1);
+        assert!((packets.accl[0].z - 9.81).abs() < 1e-6);
+        assert_eq!(packets.magn.len(), 1);
+        assert_eq!((packets.magn[0].t, packets.magn[0].x), (1.06, 20.0));
+
+        assert_eq!(packets.gps.len(), 2);
+        // minimal GPS has no time
+        assert_eq!((packets.gps[0].lat, packets.gps[0].altitude, packets.gps[0].unix_timestamp), (46.4, 990.0, 0.0));
+        let full = &packets.gps[1];
+        assert!(full.is_acquired);
+        assert_eq!(full.unix_timestamp, GPS_EPOCH + 1_370_000_000.5 - GPS_LEAP_SECONDS);
+        assert_eq!((full.lat, full.lon, full.altitude), (46.5, 6.6, 1000.5));
+        // 3 m/s east and 4 m/s north
+        assert!((full.speed - 18.0).abs() < 1e-6);
+        assert!((full.track - 36.869_897_6).abs() < 1e-6);
+        assert_eq!(packets.gps_times, Some((50.0, 1050.0)));
+    }
+
+    #[test]
+    fn rejects_other_samples() {
+        let mut packets = Packets::default();
+        assert!(read_packet(&packet(2, &f32s(&[0.1, 0.2])), 0.0, &mut packets).is_err());
+        assert!(read_packet(&packet(8, &f32s(&[0.1, 0.2, 0.3])), 0.0, &mut packets).is_err());
+        let mut reserved = packet(2, &f32s(&[0.1, 0.2, 0.3]));
+        reserved[0] = 1;
+        assert!(read_packet(&reserved, 0.0, &mut packets).is_err());
+        assert!(read_packet(&[0, 0], 0.0, &mut packets).is_err());
+        assert!(packets.gyro.is_empty());
+    }
+}
@@ -119,3 +119,48 @@ pub fn read_file(path: &str) -> std::io::Result<Vec<u8>> {
     wrapper.file.read_to_end(&mut bytes)?;
     Ok(bytes)
 }
+
+/// GoPro chapter naming: `GX020123.MP4` -> ("GX", 2, "0123"),
+/// HERO5 and older start with `GOPR0123.MP4` and continue with `GP010123.MP4`, so `GOPR` is chapter 0 of `GP`
+pub fn gopro_chapter_info(path: &str) -> Option<(String, u32, String)> {
+    let filename = get_filename(path);
+    let stem = filename.split('.').next()?.to_ascii_uppercase();
+    if stem.len() != 8 || !stem.is_ascii() || !get_extension(path).eq_ignore_ascii_case("mp4") {
+        return None;
+    }
+    let number = &stem[4..];
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if &stem[..4] == "GOPR" {
+        return Some(("GP".into(), 0, number.into()));
+    }
+    let prefix = &stem[..2];
+    if !matches!(prefix, "GP" | "GH" | "GX") {
+        return None;
+    }
+    let chapter = stem[2..4].parse::<u32>().ok()?;
+    Some((prefix.into(), chapter, number.into()))
+}
+
+/// Every chapter of the recording `path` belongs to, in the chapter order.
+/// Returns just `path` if it's not a GoPro chapter file
+pub fn gopro_chapter_paths(path: &str) -> Vec<String> {
+    let (prefix, _, number) = match gopro_chapter_info(path) {
+        Some(info) => info,
+        None => return vec![path.to_owned()]
+    };
+    let mut chapters: Vec<(u32, String)> = list_folder(&get_folder(path))
+        .into_iter()
+        .filter_map(|(_, fpath)| {
+            let (p, chapter, n) = gopro_chapter_info(&fpath)?;
+            if p == prefix && n == number { Some((chapter, fpath)) } else { None }
+        })
+        .collect();
+    chapters.sort_by_key(|(chapter, _)| *chapter);
+    chapters.dedup_by_key(|(chapter, _)| *chapter);
+    if chapters.is_empty() {
+        return vec![path.to_owned()];
+    }
+    chapters.into_iter().map(|(_, fpath)| fpath).collect()
+}
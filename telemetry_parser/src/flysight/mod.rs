@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::collections::BTreeMap;
+use std::io::*;
+use std::sync::{ Arc, atomic::AtomicBool };
+use std::path::Path;
+
+use crate::tags_impl::*;
+use crate::*;
+use crate::util::{ BARO_GROUP, GPS_EPOCH, GPS_LEAP_SECONDS };
+
+// FlySight 1: TRACK.CSV with a header, a units row and one GNSS fix per row
+// FlySight 2: TRACK.CSV (GNSS) and SENSOR.CSV (IMU, BARO, TIME, ...) in the same folder, `$` prefixed rows
+// https://github.com/flysight/flysight-2-firmware/blob/master/docs/file_formats.md
+
+const GPS_MIN_SATELLITES: f64 = 4.0;
+
+#[derive(Default)]
+pub struct FlySight {
+    pub model: Option<String>,
+    is_track: bool,
+    /// The other file of a FlySight 2 recording, SENSOR.CSV when TRACK.CSV is opened and the other way around
+    sibling_buffer: Vec<u8>,
+}
+
+impl FlySight {
+    pub fn camera_type(&self) -> String {
+        "FlySight".into()
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        false
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["csv"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    /// The FlySight 2 file read along with `filepath`
+    pub fn sidecar_paths(filepath: &str) -> Vec<String> {
+        if filesystem::get_extension(filepath) != "csv" {
+            return Vec::new();
+        }
+        let filename = filesystem::get_filename(filepath);
+        ["SENSOR.CSV", "TRACK.CSV"].iter()
+            .filter(|name| !filename.eq_ignore_ascii_case(name))
+            .filter_map(|name| sibling_path(filepath, name))
+            .collect()
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], filepath: P) -> Option<Self> {
+        if buffer.starts_with(b"time,lat,lon,hMSL,velN,velE,velD") {
+            return Some(Self { model: Some("FlySight".into()), is_track: true, sibling_buffer: Vec::new() });
+        }
+        if !buffer.starts_with(b"$FLYS") {
+            return None;
+        }
+        let header = &buffer[..buffer.len().min(2048)];
+        let is_track = memchr::memmem::find(header, b"$COL,GNSS").is_some();
+        if !is_track && memchr::memmem::find(header, b"$COL,IMU").is_none() {
+            return None;
+        }
+        let filepath = filepath.as_ref().to_str().unwrap_or_default().to_owned();
+        let sibling_buffer = sibling_path(&filepath, if is_track { "SENSOR.CSV" } else { "TRACK.CSV" })
+            .and_then(|path| filesystem::read_file(&path).ok())
+            .unwrap_or_default();
+
+        Some(Self { model: Some("FlySight 2".into()), is_track, sibling_buffer })
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, _size: usize, _progress_cb: F, _cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut buffer = Vec::new();
+        stream.read_to_end(&mut buffer)?;
+
+        let (track, sensor) = if self.is_track { (&buffer, &self.sibling_buffer) } else { (&self.sibling_buffer, &buffer) };
+
+        let mut rows = Rows::default();
+        read_rows(track, &mut rows)?;
+        read_rows(sensor, &mut rows)?;
+
+        let mut samples = Vec::new();
+
+        // sensor time is seconds from the power on, zero is the first IMU reading
+        let first_sensor_time = rows.imu.first().map(|x| x.t).or(rows.baro.first().map(|x| x.t));
+        if let Some(first) = first_sensor_time {
+            let mut map = GroupedTagMap::new();
+            let accl = rows.imu.iter().map(|x| TimeVector3 { t: x.t - first, x: x.accl[0], y: x.accl[1], z: x.accl[2] }).collect::<Vec<_>>();
+            let gyro = rows.imu.iter().map(|x| TimeVector3 { t: x.t - first, x: x.gyro[0], y: x.gyro[1], z: x.gyro[2] }).collect::<Vec<_>>();
+            let baro = rows.baro.iter().map(|x| TimeScalar { t: x.t - first, v: x.v }).collect::<Vec<_>>();
+
+            if !rows.imu.is_empty() {
+                util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Data, "Accelerometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), accl, Vec::new()));
+                util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope,     TagId::Data, "Gyroscope data",     Vec_TimeVector3_f64, |v| format!("{:?}", v), gyro, Vec::new()));
+
+                util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Unit, "Accelerometer unit", String, |v| v.to_string(), "g".into(), Vec::new()));
+                util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope,     TagId::Unit, "Gyroscope unit",     String, |v| v.to_string(), "deg/s".into(), Vec::new()));
+            }
+            if !baro.is_empty() {
+                util::insert_tag(&mut map, tag!(parsed GroupId::Custom(BARO_GROUP.into()), TagId::Data, "Pressure data", Vec_TimeScalar_f64, |v| format!("{:?}", v), baro, Vec::new()));
+                util::insert_tag(&mut map, tag!(parsed GroupId::Custom(BARO_GROUP.into()), TagId::Unit, "Pressure unit", String, |v| v.to_string(), "Pa".into(), Vec::new()));
+            }
+
+            let last = rows.imu.last().map(|x| x.t).unwrap_or(first).max(rows.baro.last().map(|x| x.t).unwrap_or(first));
+            samples.push(SampleInfo { timestamp_ms: 0.0, duration_ms: (last - first) * 1000.0, tag_map: Some(map), ..Default::default() });
+        }
+
+        if let (Some(first), Some(last)) = (rows.gps.first(), rows.gps.last()) {
+            // GNSS has the UTC time, the sensor clock is matched to it by the TIME rows
+            let timestamp_ms = match (first_sensor_time, rows.utc_offset) {
+                (Some(sensor_start), Some(offset)) => (first.unix_timestamp - (sensor_start + offset)) * 1000.0,
+                _ => 0.0
+            };
+            let duration_ms = (last.unix_timestamp - first.unix_timestamp) * 1000.0;
+
+            let mut map = GroupedTagMap::new();
+            util::insert_tag(&mut map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), rows.gps, Vec::new()));
+            samples.push(SampleInfo { timestamp_ms, duration_ms, tag_map: Some(map), ..Default::default() });
+        }
+
+        Ok(samples)
+    }
+}
+
+struct ImuRow {
+    t: f64,
+    gyro: [f64; 3],
+    accl: [f64; 3],
+}
+
+#[derive(Default)]
+struct Rows {
+    gps: Vec<GpsData>,
+    imu: Vec<ImuRow>,
+    baro: Vec<TimeScalar<f64>>,
+    /// UTC unix time - sensor time, from the first TIME row
+    utc_offset: Option<f64>,
+}
+
+fn sibling_path(filepath: &str, name: &str) -> Option<String> {
+    filesystem::list_folder(&filesystem::get_folder(filepath))
+        .into_iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, path)| path)
+}
+
+fn read_rows(buffer: &[u8], rows: &mut Rows) -> Result<()> {
+    // column names per row type, FlySight 1 rows have no type and are all GNSS
+    let mut columns: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    let mut csv = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(Cursor::new(buffer));
+
+    for row in csv.records() {
+        let row = row?;
+        let first = row.get(0).unwrap_or_default();
+        if first == "time" {
+            columns.insert("GNSS".into(), row.iter().map(|x| x.into()).collect());
+            continue;
+        }
+        if first == "$COL" && row.len() > 2 {
+            columns.insert(row[1].into(), row.iter().skip(2).map(|x| x.into()).collect());
+            continue;
+        }
+        let (kind, values) = match first.strip_prefix('$') {
+            Some(kind) => (kind, row.iter().skip(1).collect::<csv::StringRecord>()),
+            None       => ("GNSS", row.clone()),
+        };
+        let headers = match columns.get(kind) {
+            Some(h) => h,
+            None => continue
+        };
+        let map = util::create_csv_map(&values, headers);
+        let get = |name: &str| -> Option<f64> { map.get(name)?.parse::<f64>().ok() };
+
+        match kind {
+            "GNSS" => {
+                crate::try_block!({
+                    let unix_timestamp = chrono::DateTime::parse_from_rfc3339(map.get("time")?).ok()?.timestamp_micros() as f64 / 1_000_000.0;
+                    let (vel_n, vel_e) = (get("velN")?, get("velE")?);
+                    let is_acquired = match (get("gpsFix"), get("numSV")) {
+                        (Some(fix), _) => fix >= 3.0,
+                        (_, Some(sats)) => sats >= GPS_MIN_SATELLITES,
+                        _ => true
+                    };
+                    rows.gps.push(GpsData {
+                        is_acquired,
+                        unix_timestamp,
+                        lat: get("lat")?,
+                        lon: get("lon")?,
+                        altitude: get("hMSL")?,
+                        speed: (vel_n * vel_n + vel_e * vel_e).sqrt() * 3.6, // m/s to km/h
+                        track: get("heading").unwrap_or_else(|| (vel_e.atan2(vel_n).to_degrees() + 360.0) % 360.0),
+                    });
+                });
+            },
+            "IMU" => {
+                crate::try_block!({
+                    rows.imu.push(ImuRow {
+                        t: get("time")?,
+                        gyro: [get("wx")?, get("wy")?, get("wz")?],
+                        accl: [get("ax")?, get("ay")?, get("az")?],
+                    });
+                });
+            },
+            "BARO" => {
+                crate::try_block!({
+                    rows.baro.push(TimeScalar { t: get("time")?, v: get("pressure")? });
+                });
+            },
+            "TIME" if rows.utc_offset.is_none() => {
+                crate::try_block!({
+                    let utc = GPS_EPOCH + get("week")? * 604800.0 + get("tow")? - GPS_LEAP_SECONDS;
+                    rows.utc_offset = Some(utc - get("time")?);
+                });
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(flysight: &mut FlySight, buffer: &str) -> Vec<SampleInfo> {
+        flysight.parse(&mut Cursor::new(buffer.as_bytes()), buffer.len(), |_| (), Arc::new(AtomicBool::new(false))).unwrap()
+    }
+
+    fn gps_of(sample: &SampleInfo) -> Vec<GpsData> {
+        (sample.tag_map.as_ref().unwrap().get(&GroupId::GPS).unwrap().get_t(TagId::Data) as Option<&Vec<GpsData>>).unwrap().clone()
+    }
+
+    #[test]
+    fn flysight_1_track() {
+        let track = "time,lat,lon,hMSL,velN,velE,velD,hAcc,vAcc,sAcc,heading,cAcc,gpsFix,numSV\n\
+            ,(deg),(deg),(m),(m/s),(m/s),(m/s),(m),(m),(m/s),(deg),(deg),,\n\
+            2023-05-17T12:00:00.200Z,46.5,6.6,1000.5,4.0,3.0,1.0,2.0,3.0,0.5,37.0,1.0,3,10\n\
+            2023-05-17T12:00:00.400Z,46.6,6.7,1001.0,4.0,3.0,1.0,2.0,3.0,0.5,37.0,1.0,2,3\n";
+        let mut flysight = FlySight::detect(track.as_bytes(), "TRACK.CSV").unwrap();
+        let samples = parse(&mut flysight, track);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].timestamp_ms, 0.0);
+        assert!((samples[0].duration_ms - 200.0).abs() < 1e-3);
+
+        let gps = gps_of(&samples[0]);
+        assert_eq!(gps.len(), 2);
+        assert!((gps[0].unix_timestamp - 1684324800.2).abs() < 1e-6);
+        assert_eq!((gps[0].lat, gps[0].lon, gps[0].altitude, gps[0].track), (46.5, 6.6, 1000.5, 37.0));
+        assert!((gps[0].speed - 18.0).abs() < 1e-9);
+        assert_eq!(gps.iter().map(|p| p.is_acquired).collect::<Vec<_>>(), [true, false]);
+    }
+
+    #[test]
+    fn flysight_2_sensor_time_is_aligned_to_utc() {
+        let track = "$FLYS,1\n\
+            $VAR,FIRMWARE_VER,v2023.09.22\n\
+            $COL,GNSS,time,lat,lon,hMSL,velN,velE,velD,hAcc,vAcc,sAcc,numSV\n\
+            $UNIT,GNSS,,deg,deg,m,m/s,m/s,m/s,m,m,m/s,\n\
+            $DATA\n\
+            $GNSS,2023-05-17T12:00:03.000Z,46.5,6.6,1000.5,4.0,3.0,1.0,2.0,3.0,0.5,12\n\
+            $GNSS,2023-05-17T12:00:03.200Z,46.6,6.7,1001.0,0.0,-2.0,1.0,2.0,3.0,0.5,3\n";
+        let sensor = "$FLYS,1\n\
+            $COL,BARO,time,pressure,temperature\n\
+            $COL,IMU,time,wx,wy,wz,ax,ay,az,temperature\n\
+            $COL,TIME,time,tow,week\n\
+            $UNIT,IMU,s,deg/s,deg/s,deg/s,g,g,g,deg C\n\
+            $DATA\n\
+            $TIME,10.000,302418.000,2262\n\
+            $IMU,12.000,1.0,2.0,3.0,0.0,0.0,1.0,25.0\n\
+            $BARO,12.050,95000.0,20.0\n\
+            $IMU,12.010,4.0,5.0,6.0,0.0,0.1,0.9,25.0\n\
+            $TIME,11.000,302419.500,2262\n";
+        assert!(FlySight::detect(sensor.as_bytes(), "not/a/folder/SENSOR.CSV").is_some_and(|x| !x.is_track));
+
+        let mut flysight = FlySight { model: Some("FlySight 2".into()), is_track: false, sibling_buffer: track.as_bytes().to_vec() };
+        let samples = parse(&mut flysight, sensor);
+        assert_eq!(samples.len(), 2);
+
+        // zero is the first IMU reading
+        let map = samples[0].tag_map.as_ref().unwrap();
+        let gyro = (map.get(&GroupId::Gyroscope).unwrap().get_t(TagId::Data) as Option<&Vec<TimeVector3<f64>>>).unwrap();
+        assert_eq!(gyro.len(), 2);
+        assert_eq!((gyro[0].t, gyro[0].x), (0.0, 1.0));
+        assert!((gyro[1].t - 0.01).abs() < 1e-9);
+        let accl = (map.get(&GroupId::Accelerometer).unwrap().get_t(TagId::Data) as Option<&Vec<TimeVector3<f64>>>).unwrap();
+        assert_eq!(accl[1].z, 0.9);
+        let baro = (map.get(&GroupId::Custom(BARO_GROUP.into())).unwrap().get_t(TagId::Data) as Option<&Vec<TimeScalar<f64>>>).unwrap();
+        assert!((baro[0].t - 0.05).abs() < 1e-9);
+        assert!((samples[0].duration_ms - 50.0).abs() < 1e-6);
+
+        // TIME: sensor 10 s is 12:00:00 UTC, so the first IMU reading (12 s) is 12:00:02 and the first fix 1 s later
+        assert!((samples[1].timestamp_ms - 1000.0).abs() < 1e-3);
+        assert!((samples[1].duration_ms - 200.0).abs() < 1e-3);
+        let gps = gps_of(&samples[1]);
+        assert_eq!(gps[0].unix_timestamp, 1684324803.0);
+        assert_eq!(gps.iter().map(|p| p.is_acquired).collect::<Vec<_>>(), [true, false]);
+        // no heading column, the course is from the velocity: due west
+        assert!((gps[1].track - 270.0).abs() < 1e-9);
+    }
+}
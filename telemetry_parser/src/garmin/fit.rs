@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// FIT binary decoder, only what's needed to walk the records: definition and data messages,
+// compressed timestamp headers and developer fields described by `field_description` messages.
+// https://developer.garmin.com/fit/protocol/
+
+use std::collections::BTreeMap;
+use std::io::*;
+use byteorder::{ ReadBytesExt, LittleEndian };
+
+pub const MESG_FIELD_DESCRIPTION: u16 = 206;
+pub const FIELD_TIMESTAMP: u8 = 253;
+/// FIT timestamps are seconds from 1989-12-31 00:00 UTC
+pub const FIT_EPOCH: f64 = 631065600.0;
+
+const BASE_TYPE_STRING: u8 = 0x07;
+
+#[derive(Debug, Clone, Default)]
+pub struct DeveloperField {
+    pub name: String,
+    pub units: String,
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub global: u16,
+    /// From the timestamp field or the compressed header, the last known one otherwise
+    pub timestamp: Option<u32>,
+    /// Array fields have more than one value, invalid values are NaN
+    pub fields: BTreeMap<u8, Vec<f64>>,
+    pub strings: BTreeMap<u8, String>,
+    pub developer: Vec<DeveloperField>,
+}
+
+impl Message {
+    pub fn get(&self, field: u8) -> Option<f64> {
+        self.fields.get(&field)?.first().copied().filter(|v| !v.is_nan())
+    }
+    pub fn get_array(&self, field: u8) -> Option<&Vec<f64>> {
+        self.fields.get(&field).filter(|v| v.iter().any(|x| !x.is_nan()))
+    }
+}
+
+struct FieldDef {
+    num: u8,
+    size: u8,
+    base_type: u8,
+}
+
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    fields: Vec<FieldDef>,
+    /// (field number, size, developer data index)
+    developer: Vec<(u8, u8, u8)>,
+}
+
+struct DeveloperFieldDef {
+    name: String,
+    units: String,
+    base_type: u8,
+}
+
+pub fn is_fit(buffer: &[u8]) -> bool {
+    buffer.len() >= 12 && (buffer[0] == 12 || buffer[0] == 14) && &buffer[8..12] == b".FIT"
+}
+
+fn base_type_size(base_type: u8) -> usize {
+    match base_type & 0x1F {
+        0x03 | 0x04 | 0x0B => 2,
+        0x05 | 0x06 | 0x08 | 0x0C => 4,
+        0x09 | 0x0E | 0x0F | 0x10 => 8,
+        _ => 1
+    }
+}
+
+fn read_value(d: &mut &[u8], base_type: u8, big_endian: bool) -> Result<f64> {
+    macro_rules! rd {
+        ($f:ident) => { if big_endian { d.$f::<byteorder::BigEndian>()? } else { d.$f::<LittleEndian>()? } };
+    }
+    // every base type has its own invalid value, z types use 0
+    let v = match base_type & 0x1F {
+        0x01 => { let v = d.read_i8()?;  if v == i8::MAX  { f64::NAN } else { v as f64 } },
+        0x03 => { let v = rd!(read_i16); if v == i16::MAX { f64::NAN } else { v as f64 } },
+        0x04 => { let v = rd!(read_u16); if v == u16::MAX { f64::NAN } else { v as f64 } },
+        0x05 => { let v = rd!(read_i32); if v == i32::MAX { f64::NAN } else { v as f64 } },
+        0x06 => { let v = rd!(read_u32); if v == u32::MAX { f64::NAN } else { v as f64 } },
+        0x08 => { let v = rd!(read_f32); if v.to_bits() == u32::MAX { f64::NAN } else { v as f64 } },
+        0x09 => { let v = rd!(read_f64); if v.to_bits() == u64::MAX { f64::NAN } else { v } },
+        0x0A => { let v = d.read_u8()?;  if v == 0 { f64::NAN } else { v as f64 } },
+        0x0B => { let v = rd!(read_u16); if v == 0 { f64::NAN } else { v as f64 } },
+        0x0C => { let v = rd!(read_u32); if v == 0 { f64::NAN } else { v as f64 } },
+        0x0E => { let v = rd!(read_i64); if v == i64::MAX { f64::NAN } else { v as f64 } },
+        0x0F => { let v = rd!(read_u64); if v == u64::MAX { f64::NAN } else { v as f64 } },
+        0x10 => { let v = rd!(read_u64); if v == 0 { f64::NAN } else { v as f64 } },
+        0x0D => d.read_u8()? as f64,
+        _    => { let v = d.read_u8()?;  if v == u8::MAX  { f64::NAN } else { v as f64 } },
+    };
+    Ok(v)
+}
+
+fn read_values(bytes: &[u8], base_type: u8, big_endian: bool) -> Vec<f64> {
+    let size = base_type_size(base_type);
+    let base_type = if bytes.len() % size != 0 { 0x0D } else { base_type };
+    let mut d = bytes;
+    let mut values = Vec::with_capacity(bytes.len() / size);
+    while !d.is_empty() {
+        match read_value(&mut d, base_type, big_endian) {
+            Ok(v) => values.push(v),
+            Err(_) => break
+        }
+    }
+    values
+}
+
+fn read_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+fn take<'a>(d: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if d.len() < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    let (head, tail) = d.split_at(len);
+    *d = tail;
+    Ok(head)
+}
+
+/// Decodes every message of the file, a truncated file returns the messages up to the last complete one
+pub fn read_messages(buffer: &[u8]) -> Result<Vec<Message>> {
+    if !is_fit(buffer) {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a FIT file"));
+    }
+    let header_size = buffer[0] as usize;
+    if buffer.len() < header_size {
+        return Err(Error::new(ErrorKind::InvalidData, "Truncated FIT header"));
+    }
+    let data_size = (&buffer[4..8]).read_u32::<LittleEndian>()? as usize;
+    let end = (header_size + data_size).min(buffer.len());
+    let mut d = &buffer[header_size..end];
+
+    let mut definitions: BTreeMap<u8, Definition> = BTreeMap::new();
+    let mut developer_defs: BTreeMap<(u8, u8), DeveloperFieldDef> = BTreeMap::new();
+    let mut last_timestamp: Option<u32> = None;
+    let mut messages = Vec::new();
+
+    while !d.is_empty() {
+        let result = (|| -> Result<Option<Message>> {
+            let hdr = d.read_u8()?;
+            let (local, compressed_offset) = if hdr & 0x80 != 0 {
+                ((hdr >> 5) & 0x03, Some((hdr & 0x1F) as u32))
+            } else if hdr & 0x40 != 0 {
+                let has_developer = hdr & 0x20 != 0;
+                d.read_u8()?; // reserved
+                let big_endian = d.read_u8()? == 1;
+                let global = if big_endian { d.read_u16::<byteorder::BigEndian>()? } else { d.read_u16::<LittleEndian>()? };
+                let num_fields = d.read_u8()?;
+                let mut fields = Vec::with_capacity(num_fields as usize);
+                for _ in 0..num_fields {
+                    fields.push(FieldDef { num: d.read_u8()?, size: d.read_u8()?, base_type: d.read_u8()? });
+                }
+                let mut developer = Vec::new();
+                if has_developer {
+                    for _ in 0..d.read_u8()? {
+                        developer.push((d.read_u8()?, d.read_u8()?, d.read_u8()?));
+                    }
+                }
+                definitions.insert(hdr & 0x0F, Definition { global, big_endian, fields, developer });
+                return Ok(None);
+            } else {
+                (hdr & 0x0F, None)
+            };
+
+            let def = definitions.get(&local).ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Data message for undefined local type {}", local)))?;
+            let mut msg = Message { global: def.global, ..Default::default() };
+            for field in &def.fields {
+                let bytes = take(&mut d, field.size as usize)?;
+                if field.base_type & 0x1F == BASE_TYPE_STRING {
+                    msg.strings.insert(field.num, read_string(bytes));
+                } else {
+                    msg.fields.insert(field.num, read_values(bytes, field.base_type, def.big_endian));
+                }
+            }
+            for (num, size, index) in &def.developer {
+                let bytes = take(&mut d, *size as usize)?;
+                if let Some(dev) = developer_defs.get(&(*index, *num)) {
+                    if dev.base_type & 0x1F != BASE_TYPE_STRING {
+                        msg.developer.push(DeveloperField { name: dev.name.clone(), units: dev.units.clone(), values: read_values(bytes, dev.base_type, def.big_endian) });
+                    }
+                }
+            }
+
+            // compressed header has the 5 lowest bits of the time since the last full timestamp
+            if let Some(offset) = compressed_offset {
+                if let Some(last) = last_timestamp {
+                    let mut ts = (last & !0x1F) + offset;
+                    if offset < (last & 0x1F) { ts += 0x20; }
+                    last_timestamp = Some(ts);
+                }
+            } else if let Some(ts) = msg.get(FIELD_TIMESTAMP) {
+                last_timestamp = Some(ts as u32);
+            }
+            msg.timestamp = last_timestamp;
+
+            if msg.global == MESG_FIELD_DESCRIPTION {
+                if let (Some(index), Some(num), Some(base_type)) = (msg.get(0), msg.get(1), msg.get(2)) {
+                    developer_defs.insert((index as u8, num as u8), DeveloperFieldDef {
+                        name: msg.strings.get(&3).cloned().unwrap_or_else(|| format!("field {}", num)),
+                        units: msg.strings.get(&8).cloned().unwrap_or_default(),
+                        base_type: base_type as u8,
+                    });
+                }
+            }
+            Ok(Some(msg))
+        })();
+        match result {
+            Ok(Some(msg)) => messages.push(msg),
+            Ok(None) => { },
+            Err(e) => {
+                if messages.is_empty() { return Err(e); }
+                log::warn!("FIT decoding stopped after {} messages: {}", messages.len(), e);
+                break;
+            }
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fit_file(records: &[u8]) -> Vec<u8> {
+        let mut file = vec![14, 0x10, 0x00, 0x08];
+        file.extend((records.len() as u32).to_le_bytes());
+        file.extend(b".FIT");
+        file.extend([0, 0]); // header CRC, not checked
+        file.extend(records);
+        file
+    }
+
+    #[test]
+    fn decodes_definitions_data_compressed_timestamps_and_developer_fields() {
+        let mut records = Vec::new();
+        // local 0: field_description (developer_data_index, field_definition_number, fit_base_type_id, field_name, units)
+        records.extend([0x40, 0, 0, 206, 0, 5, 0, 1, 0x02, 1, 1, 0x02, 2, 1, 0x02, 3, 8, 0x07, 8, 4, 0x07]);
+        records.extend([0x00, 0, 1, 0x84]);
+        records.extend(b"power\0\0\0W\0\0\0");
+        // local 1: record with timestamp and latitude, and developer field 1 of developer 0
+        records.extend([0x61, 0, 0, 20, 0, 2, 253, 4, 0x86, 0, 4, 0x85, 1, 1, 2, 0]);
+        records.push(0x01);
+        records.extend(1000u32.to_le_bytes());
+        records.extend(123456i32.to_le_bytes());
+        records.extend(250u16.to_le_bytes());
+        // local 2: record with latitude only, for the compressed timestamp headers
+        records.extend([0x42, 0, 0, 20, 0, 1, 0, 4, 0x85]);
+        records.push(0x80 | (2 << 5) | 10);
+        records.extend(123457i32.to_le_bytes());
+        // offset 3 is below the last 5 bits (10), so it rolls over
+        records.push(0x80 | (2 << 5) | 3);
+        records.extend(i32::MAX.to_le_bytes());
+
+        let messages = read_messages(&fit_file(&records)).unwrap();
+        assert_eq!(messages.len(), 4);
+
+        assert_eq!(messages[0].global, MESG_FIELD_DESCRIPTION);
+        assert_eq!(messages[0].strings.get(&3).map(String::as_str), Some("power"));
+
+        assert_eq!(messages[1].global, 20);
+        assert_eq!(messages[1].timestamp, Some(1000));
+        assert_eq!(messages[1].get(0), Some(123456.0));
+        assert_eq!(messages[1].developer.len(), 1);
+        assert_eq!(messages[1].developer[0].name, "power");
+        assert_eq!(messages[1].developer[0].units, "W");
+        assert_eq!(messages[1].developer[0].values, vec![250.0]);
+
+        assert_eq!(messages[2].timestamp, Some(1002));
+        assert_eq!(messages[2].get(0), Some(123457.0));
+        assert_eq!(messages[3].timestamp, Some(1027));
+        assert_eq!(messages[3].get(0), None);
+    }
+
+    #[test]
+    fn truncated_header_is_invalid() {
+        let file = fit_file(&[]);
+        assert!(read_messages(&file[..13]).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+pub mod fit;
+
+use std::collections::BTreeMap;
+use std::io::*;
+use std::sync::{ Arc, atomic::AtomicBool };
+use std::path::Path;
+
+use crate::tags_impl::*;
+use crate::*;
+use fit::{ Message, FIT_EPOCH };
+
+// Garmin watches and other FIT loggers: `record` messages (position, altitude, speed),
+// `accelerometer_data`, `gyroscope_data` and `barometer_data` with the raw sensor readings
+
+pub const ALTITUDE_GROUP: &str = "Altitude";
+pub const DEVELOPER_GROUP: &str = "Developer";
+
+const MESG_FILE_ID: u16 = 0;
+const MESG_RECORD: u16 = 20;
+const MESG_GYROSCOPE_DATA: u16 = 164;
+const MESG_ACCELEROMETER_DATA: u16 = 165;
+const MESG_THREE_D_SENSOR_CALIBRATION: u16 = 167;
+const MESG_BAROMETER_DATA: u16 = 209;
+
+const MANUFACTURER_GARMIN: f64 = 1.0;
+/// semicircles to degrees
+const SEMICIRCLE: f64 = 180.0 / 2147483648.0;
+
+#[derive(Default)]
+pub struct Garmin {
+    pub model: Option<String>,
+    manufacturer: Option<f64>,
+}
+
+/// `three_d_sensor_calibration`, raw counts to g or deg/s
+#[derive(Clone)]
+struct Calibration {
+    factor: f64,
+    divisor: f64,
+    level_shift: f64,
+    offset: [f64; 3],
+    /// row major, scaled by 65535
+    orientation: Option<[f64; 9]>,
+}
+
+impl Calibration {
+    fn apply(&self, raw: [f64; 3]) -> [f64; 3] {
+        let v: Vec<f64> = raw.iter().zip(self.offset.iter()).map(|(r, o)| (r - self.level_shift - o) * self.factor / self.divisor).collect();
+        match self.orientation {
+            Some(m) => [
+                (m[0] * v[0] + m[1] * v[1] + m[2] * v[2]) / 65535.0,
+                (m[3] * v[0] + m[4] * v[1] + m[5] * v[2]) / 65535.0,
+                (m[6] * v[0] + m[7] * v[1] + m[8] * v[2]) / 65535.0,
+            ],
+            None => [v[0], v[1], v[2]]
+        }
+    }
+}
+
+impl Garmin {
+    pub fn camera_type(&self) -> String {
+        match self.manufacturer {
+            Some(m) if m != MANUFACTURER_GARMIN => "FIT".into(),
+            _ => "Garmin".into()
+        }
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        false
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["fit"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], _filepath: P) -> Option<Self> {
+        if fit::is_fit(buffer) {
+            Some(Self::default())
+        } else {
+            None
+        }
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, _size: usize, _progress_cb: F, _cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut buffer = Vec::new();
+        stream.read_to_end(&mut buffer)?;
+        let messages = fit::read_messages(&buffer)?;
+
+        let mut calibration: BTreeMap<u8, Calibration> = BTreeMap::new();
+        let mut gps = Vec::new();
+        let mut altitude = Vec::new();
+        let mut accl = Vec::new();
+        let mut gyro = Vec::new();
+        let mut baro = Vec::new();
+        let mut developer: BTreeMap<String, (String, Vec<TimeScalar<f64>>)> = BTreeMap::new();
+
+        for msg in &messages {
+            let ts = match msg.timestamp {
+                Some(ts) => ts as f64,
+                None => {
+                    if msg.global == MESG_FILE_ID { self.read_file_id(msg); }
+                    continue;
+                }
+            };
+            match msg.global {
+                MESG_FILE_ID => self.read_file_id(msg),
+                MESG_RECORD => {
+                    // enhanced fields are u32, the old ones overflow at 9 km altitude and 65 m/s
+                    let alt = msg.get(78).or(msg.get(2)).map(|v| v / 5.0 - 500.0);
+                    if let Some(alt) = alt {
+                        altitude.push(TimeScalar { t: ts, v: alt });
+                    }
+                    if let (Some(lat), Some(lon)) = (msg.get(0), msg.get(1)) {
+                        gps.push(GpsData {
+                            is_acquired: true,
+                            unix_timestamp: ts + FIT_EPOCH,
+                            lat: lat * SEMICIRCLE,
+                            lon: lon * SEMICIRCLE,
+                            altitude: alt.unwrap_or_default(),
+                            speed: msg.get(73).or(msg.get(6)).map(|v| v / 1000.0 * 3.6).unwrap_or_default(), // mm/s to km/h
+                            track: 0.0,
+                        });
+                    }
+                },
+                MESG_THREE_D_SENSOR_CALIBRATION => {
+                    crate::try_block!({
+                        let offset = msg.get_array(4).filter(|v| v.len() >= 3).map(|v| [v[0], v[1], v[2]]).unwrap_or_default();
+                        calibration.insert(msg.get(0)? as u8, Calibration {
+                            factor: msg.get(1)?,
+                            divisor: msg.get(2).filter(|v| *v != 0.0).unwrap_or(1.0),
+                            level_shift: msg.get(3).unwrap_or_default(),
+                            offset,
+                            orientation: msg.get_array(5).filter(|v| v.len() >= 9).map(|v| [v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8]]),
+                        });
+                    });
+                },
+                MESG_ACCELEROMETER_DATA => accl.extend(read_imu(msg, ts, calibration.get(&0))),
+                MESG_GYROSCOPE_DATA     => gyro.extend(read_imu(msg, ts, calibration.get(&1))),
+                MESG_BAROMETER_DATA => {
+                    if let Some(pressure) = msg.get_array(2) {
+                        for (t, v) in sample_times(msg, ts, pressure.len()).into_iter().zip(pressure) {
+                            if !v.is_nan() { baro.push(TimeScalar { t, v: *v }); }
+                        }
+                    }
+                },
+                _ => { }
+            }
+            for field in &msg.developer {
+                if let Some(v) = field.values.first().filter(|v| !v.is_nan()) {
+                    developer.entry(field.name.clone()).or_insert_with(|| (field.units.clone(), Vec::new())).1.push(TimeScalar { t: ts, v: *v });
+                }
+            }
+        }
+
+        // zero is the first IMU reading, or the first record when there's no IMU
+        let start = accl.first().or(gyro.first()).map(|v: &TimeVector3<f64>| v.t)
+            .or(altitude.first().map(|v| v.t))
+            .or(messages.iter().find_map(|m| m.timestamp).map(|t| t as f64));
+        let start = match start {
+            Some(start) => start,
+            None => return Ok(Vec::new())
+        };
+        let relative = |v: &mut Vec<TimeScalar<f64>>| v.iter_mut().for_each(|x| x.t -= start);
+        accl.iter_mut().chain(gyro.iter_mut()).for_each(|x| x.t -= start);
+        relative(&mut altitude);
+        relative(&mut baro);
+
+        let mut samples = Vec::new();
+
+        let mut map = GroupedTagMap::new();
+        if !accl.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Data, "Accelerometer data", Vec_TimeVector3_f64, |v| format!("{:?}", v), accl, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Accelerometer, TagId::Unit, "Accelerometer unit", String, |v| v.to_string(), "g".into(), Vec::new()));
+        }
+        if !gyro.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Data, "Gyroscope data", Vec_TimeVector3_f64, |v| format!("{:?}", v), gyro, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Gyroscope, TagId::Unit, "Gyroscope unit", String, |v| v.to_string(), "deg/s".into(), Vec::new()));
+        }
+        if !altitude.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(ALTITUDE_GROUP.into()), TagId::Data, "Altitude data", Vec_TimeScalar_f64, |v| format!("{:?}", v), altitude, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(ALTITUDE_GROUP.into()), TagId::Unit, "Altitude unit", String, |v| v.to_string(), "m".into(), Vec::new()));
+        }
+        if !baro.is_empty() {
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(util::BARO_GROUP.into()), TagId::Data, "Pressure data", Vec_TimeScalar_f64, |v| format!("{:?}", v), baro, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(util::BARO_GROUP.into()), TagId::Unit, "Pressure unit", String, |v| v.to_string(), "Pa".into(), Vec::new()));
+        }
+        for (name, (units, mut values)) in developer {
+            relative(&mut values);
+            util::insert_tag(&mut map, tag!(parsed GroupId::Custom(DEVELOPER_GROUP.into()), TagId::Custom(name.clone()), &format!("{} ({})", name, units), Vec_TimeScalar_f64, |v| format!("{:?}", v), values, Vec::new()));
+        }
+        if !map.is_empty() {
+            samples.push(SampleInfo { timestamp_ms: 0.0, tag_map: Some(map), ..Default::default() });
+        }
+
+        if let (Some(first), Some(last)) = (gps.first(), gps.last()) {
+            let timestamp_ms = (first.unix_timestamp - FIT_EPOCH - start) * 1000.0;
+            let duration_ms = (last.unix_timestamp - first.unix_timestamp) * 1000.0;
+            util::fill_track(&mut gps, &mut None);
+
+            let mut map = GroupedTagMap::new();
+            util::insert_tag(&mut map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), gps, Vec::new()));
+            samples.push(SampleInfo { timestamp_ms, duration_ms, tag_map: Some(map), ..Default::default() });
+        }
+
+        Ok(samples)
+    }
+
+    fn read_file_id(&mut self, msg: &Message) {
+        self.manufacturer = msg.get(1);
+        self.model = msg.strings.get(&8).cloned().or_else(|| msg.get(2).map(|product| format!("product {}", product)));
+    }
+}
+
+/// Sensor messages have the time of the first reading in `timestamp` + `timestamp_ms` and the offsets of all of them in ms
+fn sample_times(msg: &Message, ts: f64, count: usize) -> Vec<f64> {
+    let base = ts + msg.get(0).unwrap_or_default() / 1000.0;
+    let offsets = msg.get_array(1);
+    (0..count).map(|i| base + offsets.and_then(|o| o.get(i)).copied().filter(|v| !v.is_nan()).unwrap_or_default() / 1000.0).collect()
+}
+
+/// Calibrated values (fields 5, 6, 7) if the device wrote them, the raw counts (2, 3, 4) with the calibration otherwise
+fn read_imu(msg: &Message, ts: f64, calibration: Option<&Calibration>) -> Vec<TimeVector3<f64>> {
+    let (axes, calibration) = match (msg.get_array(5), msg.get_array(6), msg.get_array(7)) {
+        (Some(x), Some(y), Some(z)) => ([x, y, z], None),
+        _ => match (msg.get_array(2), msg.get_array(3), msg.get_array(4), calibration) {
+            (Some(x), Some(y), Some(z), Some(c)) => ([x, y, z], Some(c)),
+            _ => return Vec::new()
+        }
+    };
+    let count = axes.iter().map(|a| a.len()).min().unwrap_or_default();
+    sample_times(msg, ts, count).into_iter().enumerate().filter_map(|(i, t)| {
+        let mut v = [axes[0][i], axes[1][i], axes[2][i]];
+        if v.iter().any(|x| x.is_nan()) { return None; }
+        if let Some(c) = calibration { v = c.apply(v); }
+        Some(TimeVector3 { t, x: v[0], y: v[1], z: v[2] })
+    }).collect()
+}
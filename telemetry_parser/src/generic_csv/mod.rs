@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::io::*;
+use std::sync::{ Arc, atomic::AtomicBool };
+use std::path::Path;
+use serde::Deserialize;
+
+use crate::tags_impl::*;
+use crate::*;
+
+// CSV from phone apps and DIY loggers, the columns are named by a JSON or TOML mapping:
+// a sidecar `<log name>.json`/`.toml` or `csv_mapping.json`/`.toml` in the same folder,
+// or the one passed to `Input::from_stream_with_csv_mapping`
+//
+// {
+//     "name": "phyphox",
+//     "time": { "column": "Time (s)", "unit": "s" },
+//     "accelerometer": { "columns": ["ax", "ay", "az"], "unit": "m/s²" },
+//     "gyroscope": { "columns": ["wx", "wy", "wz"], "unit": "rad/s", "scale": 1.0 },
+//     "orientation": "XYZ",
+//     "gps": { "lat": "Latitude", "lon": "Longitude", "altitude": "Height", "speed": "Speed", "speed_unit": "m/s" }
+// }
+
+pub const FOLDER_MAPPING: &str = "csv_mapping";
+/// `time.unit` values
+pub const TIME_UNITS: [&str; 4] = ["s", "ms", "us", "ns"];
+/// Mapping file extensions, in the order they are looked up
+pub const MAPPING_EXTENSIONS: [&str; 2] = ["json", "toml"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeColumn {
+    pub column: String,
+    /// s, ms, us or ns
+    #[serde(default = "default_time_unit")]
+    pub unit: String,
+}
+fn default_time_unit() -> String { "s".into() }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SensorColumns {
+    pub columns: [String; 3],
+    /// multiplier of the column values
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// g or m/s² for the accelerometer, rad/s or deg/s for the gyroscope
+    pub unit: Option<String>,
+}
+fn default_scale() -> f64 { 1.0 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GpsColumns {
+    pub lat: String,
+    pub lon: String,
+    pub altitude: Option<String>,
+    pub speed: Option<String>,
+    /// m/s or km/h
+    #[serde(default = "default_speed_unit")]
+    pub speed_unit: String,
+    pub track: Option<String>,
+    /// unix time in seconds, `GpsData::unix_timestamp` is 0 without it
+    pub unix_time: Option<String>,
+}
+fn default_speed_unit() -> String { "m/s".into() }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvMapping {
+    pub name: Option<String>,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// rows before the header
+    #[serde(default)]
+    pub skip_rows: usize,
+    pub time: TimeColumn,
+    pub accelerometer: Option<SensorColumns>,
+    pub gyroscope: Option<SensorColumns>,
+    pub magnetometer: Option<SensorColumns>,
+    /// IMU orientation as in .gcsv, `XYZ` by default
+    pub orientation: Option<String>,
+    pub gps: Option<GpsColumns>,
+}
+fn default_delimiter() -> char { ',' }
+
+impl CsvMapping {
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        let mapping: Self = serde_json::from_slice(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid CSV mapping: {}", e)))?;
+        mapping.validated()
+    }
+    pub fn from_toml(bytes: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid CSV mapping: {}", e)))?;
+        let mapping: Self = toml::from_str(text).map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid CSV mapping: {}", e)))?;
+        mapping.validated()
+    }
+    /// TOML for a `.toml` file, JSON otherwise
+    pub fn from_file(path: &str) -> Result<Self> {
+        let bytes = filesystem::read_file(path)?;
+        if filesystem::get_extension(path) == "toml" {
+            Self::from_toml(&bytes)
+        } else {
+            Self::from_json(&bytes)
+        }
+    }
+
+    fn validated(self) -> Result<Self> {
+        if !TIME_UNITS.contains(&self.time.unit.as_str()) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Invalid CSV mapping: unknown time unit \"{}\", expected one of {:?}", self.time.unit, TIME_UNITS)));
+        }
+        Ok(self)
+    }
+
+    fn time_scale(&self) -> f64 {
+        match self.time.unit.as_str() {
+            "ms" => 0.001,
+            "us" => 0.000_001,
+            "ns" => 0.000_000_001,
+            _ => 1.0
+        }
+    }
+
+    fn reader<R: Read>(&self, stream: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .delimiter(self.delimiter as u8)
+            .from_reader(stream)
+    }
+}
+
+#[derive(Default)]
+pub struct GenericCsv {
+    pub model: Option<String>,
+    mapping: Option<CsvMapping>,
+}
+
+impl GenericCsv {
+    pub fn camera_type(&self) -> String {
+        "CSV".into()
+    }
+    pub fn has_accurate_timestamps(&self) -> bool {
+        false
+    }
+    pub fn possible_extensions() -> Vec<&'static str> {
+        vec!["csv", "txt"]
+    }
+    pub fn frame_readout_time(&self) -> Option<f64> {
+        None
+    }
+    pub fn normalize_imu_orientation(v: String) -> String {
+        v
+    }
+
+    /// The mapping file used for `filepath`
+    pub fn sidecar_paths(filepath: &str) -> Vec<String> {
+        let ext = filesystem::get_extension(filepath);
+        if !ext.is_empty() && !Self::possible_extensions().contains(&ext.as_str()) {
+            return Vec::new();
+        }
+        MAPPING_EXTENSIONS.iter().find_map(|ext| filesystem::file_with_extension(filepath, ext)).or_else(|| {
+            let folder = filesystem::list_folder(&filesystem::get_folder(filepath));
+            MAPPING_EXTENSIONS.iter().find_map(|ext| {
+                let name = format!("{}.{}", FOLDER_MAPPING, ext);
+                folder.iter().find(|(x, _)| *x == name).map(|(_, path)| path.clone())
+            })
+        }).into_iter().collect()
+    }
+
+    pub fn detect<P: AsRef<Path>>(buffer: &[u8], filepath: P) -> Option<Self> {
+        let filepath = filepath.as_ref().to_str().unwrap_or_default().to_owned();
+
+        let path = Self::sidecar_paths(&filepath).pop()?;
+        match CsvMapping::from_file(&path) {
+            Ok(mapping) => Self::with_mapping(buffer, mapping),
+            Err(e) => { log::warn!("{}: {}", path, e); None }
+        }
+    }
+
+    /// CSV read with `mapping`, `None` when the mapping doesn't fit the header
+    pub fn with_mapping(buffer: &[u8], mapping: CsvMapping) -> Option<Self> {
+        // the mapping has to fit the header
+        let header = mapping.reader(buffer).records().nth(mapping.skip_rows)?.ok()?;
+        if !header.iter().any(|x| x == mapping.time.column) {
+            return None;
+        }
+        Some(Self { model: mapping.name.clone(), mapping: Some(mapping) })
+    }
+
+    pub fn parse<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, _size: usize, _progress_cb: F, _cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mapping = self.mapping.as_ref().ok_or_else(|| Error::new(ErrorKind::InvalidData, "No CSV mapping"))?;
+        let time_scale = mapping.time_scale();
+
+        let mut headers = None;
+        let mut start = None;
+        let mut accl = Vec::new();
+        let mut gyro = Vec::new();
+        let mut magn = Vec::new();
+        let mut gps: Vec<(f64, GpsData)> = Vec::new();
+
+        for row in mapping.reader(stream).records().skip(mapping.skip_rows) {
+            let row = row?;
+            let h = match headers {
+                Some(ref h) => h,
+                None => {
+                    headers = Some(row.iter().map(|x| x.to_owned()).collect::<Vec<String>>());
+                    continue;
+                }
+            };
+            let map = util::create_csv_map(&row, h);
+            let get = |name: &str| -> Option<f64> { map.get(name)?.parse::<f64>().ok() };
+
+            let t = match get(&mapping.time.column) {
+                Some(t) => t * time_scale,
+                None => continue
+            };
+            let t = t - *start.get_or_insert(t);
+
+            let read = |columns: &Option<SensorColumns>| -> Option<TimeVector3<f64>> {
+                let c = columns.as_ref()?;
+                Some(TimeVector3 { t, x: get(&c.columns[0])? * c.scale, y: get(&c.columns[1])? * c.scale, z: get(&c.columns[2])? * c.scale })
+            };
+            if let Some(v) = read(&mapping.accelerometer) { accl.push(v); }
+            if let Some(v) = read(&mapping.gyroscope)     { gyro.push(v); }
+            if let Some(v) = read(&mapping.magnetometer)  { magn.push(v); }
+
+            if let Some(g) = &mapping.gps {
+                crate::try_block!({
+                    let speed = g.speed.as_ref().and_then(|c| get(c)).unwrap_or_default();
+                    gps.push((t, GpsData {
+                        is_acquired: true,
+                        unix_timestamp: g.unix_time.as_ref().and_then(|c| get(c)).unwrap_or_default(),
+                        lat: get(&g.lat)?,
+                        lon: get(&g.lon)?,
+                        altitude: g.altitude.as_ref().and_then(|c| get(c)).unwrap_or_default(),
+                        speed: if g.speed_unit == "km/h" { speed } else { speed * 3.6 },
+                        track: g.track.as_ref().and_then(|c| get(c)).unwrap_or_default(),
+                    }));
+                });
+            }
+        }
+
+        let mut samples = Vec::new();
+
+        let mut map = GroupedTagMap::new();
+        let orientation = mapping.orientation.clone().unwrap_or_else(|| "XYZ".into());
+        let sensors = [
+            (GroupId::Accelerometer, "Accelerometer", accl, &mapping.accelerometer, "m/s²"),
+            (GroupId::Gyroscope,     "Gyroscope",     gyro, &mapping.gyroscope,     "deg/s"),
+            (GroupId::Magnetometer,  "Magnetometer",  magn, &mapping.magnetometer,  "μT"),
+        ];
+        for (group, name, data, columns, default_unit) in sensors {
+            if data.is_empty() { continue; }
+            let unit = columns.as_ref().and_then(|c| c.unit.clone()).unwrap_or_else(|| default_unit.into());
+            util::insert_tag(&mut map, tag!(parsed group.clone(), TagId::Data,        &format!("{} data", name), Vec_TimeVector3_f64, |v| format!("{:?}", v), data, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed group.clone(), TagId::Unit,        &format!("{} unit", name), String, |v| v.to_string(), unit, Vec::new()));
+            util::insert_tag(&mut map, tag!(parsed group,         TagId::Orientation, "IMU orientation",         String, |v| v.to_string(), orientation.clone(), Vec::new()));
+        }
+        if !map.is_empty() {
+            samples.push(SampleInfo { timestamp_ms: 0.0, tag_map: Some(map), ..Default::default() });
+        }
+
+        if let (Some(first), Some(last)) = (gps.first(), gps.last()) {
+            let timestamp_ms = first.0 * 1000.0;
+            let duration_ms = (last.0 - first.0) * 1000.0;
+            let gps = gps.into_iter().map(|v| v.1).collect::<Vec<GpsData>>();
+
+            let mut map = GroupedTagMap::new();
+            util::insert_tag(&mut map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), gps, Vec::new()));
+            samples.push(SampleInfo { timestamp_ms, duration_ms, tag_map: Some(map), ..Default::default() });
+        }
+
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "phyphox export\n\
+        t,ax,ay,az,lat,lon\n\
+        1500,1,2,3,46.5,6.6\n\
+        1510,2,4,6,46.6,6.7\n\
+        1520,3,6,9,,\n";
+
+    const JSON: &str = r#"{
+        "name": "phyphox",
+        "skip_rows": 1,
+        "time": { "column": "t", "unit": "ms" },
+        "accelerometer": { "columns": ["ax", "ay", "az"], "unit": "g", "scale": 0.5 },
+        "gps": { "lat": "lat", "lon": "lon" }
+    }"#;
+
+    const TOML: &str = r#"
+        name = "phyphox"
+        skip_rows = 1
+        [time]
+        column = "t"
+        unit = "ms"
+        [accelerometer]
+        columns = ["ax", "ay", "az"]
+        unit = "g"
+        scale = 0.5
+        [gps]
+        lat = "lat"
+        lon = "lon"
+    "#;
+
+    fn parse(mapping: CsvMapping) -> Vec<SampleInfo> {
+        let mut csv = GenericCsv::with_mapping(CSV.as_bytes(), mapping).unwrap();
+        csv.parse(&mut Cursor::new(CSV.as_bytes()), CSV.len(), |_| (), Arc::new(AtomicBool::new(false))).unwrap()
+    }
+
+    fn accl(samples: &[SampleInfo]) -> Vec<TimeVector3<f64>> {
+        let map = samples[0].tag_map.as_ref().unwrap().get(&GroupId::Accelerometer).unwrap();
+        (map.get_t(TagId::Data) as Option<&Vec<TimeVector3<f64>>>).unwrap().clone()
+    }
+
+    #[test]
+    fn json_and_toml_mappings_read_the_same_values() {
+        for mapping in [CsvMapping::from_json(JSON.as_bytes()).unwrap(), CsvMapping::from_toml(TOML.as_bytes()).unwrap()] {
+            let samples = parse(mapping);
+            assert_eq!(samples.len(), 2);
+
+            // the time starts at the first row, in seconds
+            let accl = accl(&samples);
+            assert_eq!(accl.iter().map(|v| (v.t * 1000.0).round()).collect::<Vec<_>>(), [0.0, 10.0, 20.0]);
+            assert_eq!((accl[1].x, accl[1].y, accl[1].z), (1.0, 2.0, 3.0));
+            let unit = samples[0].tag_map.as_ref().unwrap().get(&GroupId::Accelerometer).unwrap().get_t(TagId::Unit) as Option<&String>;
+            assert_eq!(unit.map(String::as_str), Some("g"));
+
+            // the row without a position is skipped, no unix time column leaves the timestamp unset
+            let gps = samples[1].tag_map.as_ref().unwrap().get(&GroupId::GPS).unwrap().get_t(TagId::Data) as Option<&Vec<GpsData>>;
+            let gps = gps.unwrap();
+            assert_eq!(gps.len(), 2);
+            assert_eq!((gps[1].lat, gps[1].lon), (46.6, 6.7));
+            assert_eq!(gps[1].unix_timestamp, 0.0);
+            assert!((samples[1].duration_ms - 10.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn with_mapping_rejects_another_header() {
+        let mapping = CsvMapping::from_json(br#"{ "time": { "column": "Time (s)" } }"#).unwrap();
+        assert!(GenericCsv::with_mapping(CSV.as_bytes(), mapping).is_none());
+        // the header is after `skip_rows`
+        let mapping = CsvMapping::from_json(br#"{ "time": { "column": "t" } }"#).unwrap();
+        assert!(GenericCsv::with_mapping(CSV.as_bytes(), mapping).is_none());
+    }
+
+    #[test]
+    fn unknown_time_unit_is_an_error() {
+        let e = CsvMapping::from_json(br#"{ "time": { "column": "t", "unit": "msec" } }"#).unwrap_err();
+        assert!(e.to_string().contains("\"msec\""), "{}", e);
+        assert!(CsvMapping::from_toml("[time]\ncolumn = \"t\"\nunit = \"µs\"".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn sidecar_mapping_is_used_before_the_explicit_one() {
+        let dir = std::env::temp_dir().join(format!("generic_csv_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.csv");
+        std::fs::write(&path, CSV).unwrap();
+        let open = |explicit: Option<&CsvMapping>| {
+            Input::from_stream_with_csv_mapping(&mut Cursor::new(CSV.as_bytes()), CSV.len(), &path, explicit, |_| (), Arc::new(AtomicBool::new(false)))
+        };
+        let explicit = CsvMapping::from_toml(TOML.replace("\"phyphox\"", "\"explicit\"").as_bytes()).unwrap();
+
+        assert!(open(None).is_err());
+        assert_eq!(open(Some(&explicit)).unwrap().camera_model().map(String::as_str), Some("explicit"));
+
+        std::fs::write(dir.join("log.toml"), TOML).unwrap();
+        assert_eq!(GenericCsv::sidecar_paths(path.to_str().unwrap()), [dir.join("log.toml").to_string_lossy()]);
+        assert_eq!(open(Some(&explicit)).unwrap().camera_model().map(String::as_str), Some("phyphox"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// GPS5 (up to HERO10) and GPS9 (HERO11+) streams converted to `Vec<GpsData>` in `GroupId::GPS`, like the other cameras.
+// https://github.com/gopro/gpmf-parser#where-to-find-gps-data
+
+use crate::tags_impl::*;
+use crate::util::{ insert_tag, fill_track, SampleInfo };
+use crate::tag;
+
+const GPSF: TagId = TagId::Unknown(0x47505346); // fix: 0 none, 2 2D, 3 3D
+const GPSU: TagId = TagId::Unknown(0x47505355); // UTC time of the first GPS5 sample
+const GPSP: TagId = TagId::Unknown(0x47505350); // DOP * 100
+
+pub const GPS9_GROUP: &str = "GPS9";
+/// Fixes with a worse dilution of precision are not acquired, GoPro recommends under 5
+pub const GPS_MAX_DOP: f64 = 5.0;
+/// GPMF payloads are about 1 s, used when the sample has no duration (raw .gpmf)
+const DEF_PAYLOAD_DURATION_MS: f64 = 1000.0;
+/// GPS9 days are counted from 2000-01-01
+const UNIX_2000: f64 = 946684800.0;
+
+fn get_scales(map: &TagMap) -> Vec<f64> {
+    if let Some(v) = map.get_t(TagId::Scale) as Option<&Vec<i32>> {
+        return v.iter().map(|x| *x as f64).collect();
+    }
+    if let Some(v) = map.get_t(TagId::Scale) as Option<&i32> {
+        return vec![*v as f64];
+    }
+    Vec::new()
+}
+
+/// `row[i] / scales[i]`, a single scale applies to every field
+fn scaled(row: &[f64], scales: &[f64]) -> Vec<f64> {
+    row.iter().enumerate().map(|(i, v)| {
+        let scale = scales.get(i).or(scales.last()).copied().unwrap_or(1.0);
+        if scale != 0.0 { v / scale } else { *v }
+    }).collect()
+}
+
+fn is_acquired(fix: f64, dop: Option<f64>) -> bool {
+    fix >= 2.0 && dop.map(|dop| dop <= GPS_MAX_DOP).unwrap_or(true)
+}
+
+/// lat, lon, alt, 2D speed, 3D speed; one fix, DOP and start time for the whole payload
+fn gps5_to_gps_data(map: &TagMap, duration_ms: f64) -> Option<Vec<GpsData>> {
+    let rows = (map.get_t(TagId::Data) as Option<&Vec<Vec<i32>>>)?;
+    let start_ms = *(map.get_t(GPSU) as Option<&u64>)? as f64;
+    let scales = get_scales(map);
+    let fix = (map.get_t(GPSF) as Option<&u32>).map(|x| *x as f64).unwrap_or(0.0);
+    let dop = (map.get_t(GPSP) as Option<&u16>).map(|x| *x as f64 / 100.0);
+
+    let duration_ms = if duration_ms > 0.0 { duration_ms } else { DEF_PAYLOAD_DURATION_MS };
+    let step_ms = duration_ms / rows.len().max(1) as f64;
+    Some(rows.iter().enumerate().filter(|(_, row)| row.len() >= 4).map(|(i, row)| {
+        let v = scaled(&row.iter().map(|x| *x as f64).collect::<Vec<f64>>(), &scales);
+        GpsData {
+            is_acquired: is_acquired(fix, dop),
+            unix_timestamp: (start_ms + i as f64 * step_ms) / 1000.0,
+            lat: v[0],
+            lon: v[1],
+            altitude: v[2],
+            speed: v[3] * 3.6, // m/s to km/h
+            track: 0.0,
+        }
+    }).collect())
+}
+
+/// lat, lon, alt, 2D speed, 3D speed, days since 2000, sec since midnight, DOP, fix; all per sample
+fn gps9_to_gps_data(map: &TagMap) -> Option<Vec<GpsData>> {
+    // a payload with one fix is parsed as a single row
+    let rows = match (map.get_t(TagId::Data) as Option<&Vec<Vec<Scalar>>>, map.get_t(TagId::Data) as Option<&Vec<Scalar>>) {
+        (Some(rows), _) => rows.clone(),
+        (_, Some(row))  => vec![row.clone()],
+        _ => return None,
+    };
+    let scales = get_scales(map);
+    Some(rows.iter().filter(|row| row.len() >= 9).map(|row| {
+        let v = scaled(&row.iter().map(|x| x.as_f64().unwrap_or_default()).collect::<Vec<f64>>(), &scales);
+        GpsData {
+            is_acquired: is_acquired(v[8], Some(v[7])),
+            unix_timestamp: UNIX_2000 + v[5] * 86400.0 + v[6],
+            lat: v[0],
+            lon: v[1],
+            altitude: v[2],
+            speed: v[3] * 3.6, // m/s to km/h
+            track: 0.0,
+        }
+    }).collect())
+}
+
+/// Adds `Vec_GpsData` to `GroupId::GPS` of every sample with a GPS5 or GPS9 stream, GPS9 is preferred when there are both.
+/// The raw GPS5 values stay in the tag `raw_data`, GPS9 stays in the `GPS9_GROUP` group
+pub fn process_gps(samples: &mut [SampleInfo]) {
+    let mut prev: Option<GpsData> = None;
+    for info in samples.iter_mut() {
+        let duration_ms = info.duration_ms;
+        let grouped_tag_map = match info.tag_map.as_mut() {
+            Some(map) => map,
+            None => continue,
+        };
+        let gps9 = grouped_tag_map.get(&GroupId::Custom(GPS9_GROUP.into())).and_then(|map| {
+            Some((gps9_to_gps_data(map)?, map.get(&TagId::Data)?.value.raw_data().to_vec()))
+        });
+        let gps5 = || grouped_tag_map.get(&GroupId::GPS).and_then(|map| {
+            Some((gps5_to_gps_data(map, duration_ms)?, map.get(&TagId::Data)?.value.raw_data().to_vec()))
+        });
+        let (mut gps, raw) = match gps9.or_else(gps5) {
+            Some(x) => x,
+            None => continue,
+        };
+        fill_track(&mut gps, &mut prev);
+        insert_tag(grouped_tag_map, tag!(parsed GroupId::GPS, TagId::Data, "GPS data", Vec_GpsData, |v| format!("{:?}", v), gps, raw));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gopro::GoPro;
+    use crate::gopro::tests::klv;
+
+    fn be_bytes(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_be_bytes()).collect()
+    }
+
+    /// Sample of one payload with the `STRM` of `stream`, `process_gps` applied
+    fn gps_of(stream: Vec<u8>) -> Vec<GpsData> {
+        let map = GoPro::parse_metadata(&klv(b"STRM", 0, 1, &stream), GroupId::Default, false).unwrap();
+        let mut samples = [SampleInfo { duration_ms: 1000.0, tag_map: Some(map), ..Default::default() }];
+        process_gps(&mut samples);
+        let gps = samples[0].tag_map.as_ref().unwrap().get(&GroupId::GPS).unwrap().get_t(TagId::Data) as Option<&Vec<GpsData>>;
+        gps.unwrap().clone()
+    }
+
+    fn gps5(fix: u32, dop: u16, rows: &[[i32; 5]]) -> Vec<u8> {
+        let mut stream = klv(b"GPSF", b'L', 4, &fix.to_be_bytes());
+        stream.extend(klv(b"GPSU", b'U', 16, b"230615120000.500"));
+        stream.extend(klv(b"GPSP", b'S', 2, &dop.to_be_bytes()));
+        stream.extend(klv(b"SCAL", b'l', 4, &be_bytes(&[10_000_000, 10_000_000, 1000, 1000, 100])));
+        stream.extend(klv(b"GPS5", b'l', 20, &rows.iter().flat_map(|row| be_bytes(row)).collect::<Vec<u8>>()));
+        stream
+    }
+
+    fn gps9(rows: &[([i32; 7], [u16; 2])]) -> Vec<u8> {
+        let mut stream = klv(b"SCAL", b'l', 4, &be_bytes(&[10_000_000, 10_000_000, 1000, 1000, 100, 1, 1000, 100, 1]));
+        stream.extend(klv(b"TYPE", b'c', 1, b"lllllllSS"));
+        let data = rows.iter().flat_map(|(l, s)| {
+            let mut row = be_bytes(l);
+            row.extend(s.iter().flat_map(|x| x.to_be_bytes()));
+            row
+        }).collect::<Vec<u8>>();
+        stream.extend(klv(b"GPS9", b'?', 32, &data));
+        stream
+    }
+
+    #[test]
+    fn scaled_by_one_or_several_scales() {
+        assert_eq!(scaled(&[10.0, 20.0, 30.0], &[10.0]), [1.0, 2.0, 3.0]);
+        assert_eq!(scaled(&[10.0, 20.0, 30.0], &[10.0, 100.0]), [1.0, 0.2, 0.3]);
+        assert_eq!(scaled(&[10.0, 20.0], &[0.0, 2.0]), [10.0, 10.0]);
+        assert_eq!(scaled(&[10.0], &[]), [10.0]);
+    }
+
+    #[test]
+    fn gps5_rows_spread_over_the_payload() {
+        let gps = gps_of(gps5(3, 150, &[[465_000_000, 66_000_000, 1_000_500, 10_000, 1000], [466_000_000, 67_000_000, 1_001_000, 20_000, 2000]]));
+        assert_eq!(gps.len(), 2);
+        assert!(gps.iter().all(|p| p.is_acquired));
+        assert_eq!((gps[0].lat, gps[0].lon, gps[0].altitude), (46.5, 6.6, 1000.5));
+        assert!((gps[0].speed - 36.0).abs() < 1e-9);
+        assert_eq!(gps[0].unix_timestamp, 1686830400.5);
+        assert_eq!(gps[1].unix_timestamp, 1686830401.0);
+        assert_eq!(gps[0].track, 0.0);
+        assert!(gps[1].track > 0.0 && gps[1].track < 90.0);
+    }
+
+    #[test]
+    fn gps5_without_fix_or_with_a_high_dop_is_not_acquired() {
+        let row = [[465_000_000, 66_000_000, 1_000_500, 10_000, 1000]];
+        assert!(!gps_of(gps5(0, 150, &row))[0].is_acquired);
+        assert!(!gps_of(gps5(3, (GPS_MAX_DOP * 100.0) as u16 + 1, &row))[0].is_acquired);
+        assert!(gps_of(gps5(2, (GPS_MAX_DOP * 100.0) as u16, &row))[0].is_acquired);
+    }
+
+    #[test]
+    fn gps9_time_dop_and_fix_per_sample() {
+        let gps = gps_of(gps9(&[
+            ([465_000_000, 66_000_000, 1_000_500, 10_000, 1000, 8566, 43_200_250], [150, 3]),
+            ([465_000_100, 66_000_100, 1_000_600, 10_000, 1000, 8566, 43_200_350], [600, 3]),
+            ([465_000_200, 66_000_200, 1_000_700, 10_000, 1000, 8566, 43_200_450], [150, 0]),
+        ]));
+        assert_eq!(gps.len(), 3);
+        assert_eq!((gps[0].lat, gps[0].lon, gps[0].altitude), (46.5, 6.6, 1000.5));
+        assert!((gps[0].speed - 36.0).abs() < 1e-9);
+        // 2023-06-15 12:00:00.250 UTC
+        assert!((gps[0].unix_timestamp - 1686830400.25).abs() < 1e-6);
+        assert!((gps[1].unix_timestamp - 1686830400.35).abs() < 1e-6);
+        assert_eq!(gps.iter().map(|p| p.is_acquired).collect::<Vec<_>>(), [true, false, false]);
+    }
+
+    #[test]
+    fn gps9_single_row_payload() {
+        let gps = gps_of(gps9(&[([465_000_000, 66_000_000, 1_000_500, 10_000, 1000, 8566, 43_200_250], [150, 3])]));
+        assert_eq!(gps.len(), 1);
+        assert!(gps[0].is_acquired);
+        assert_eq!((gps[0].lat, gps[0].lon), (46.5, 6.6));
+        assert!((gps[0].unix_timestamp - 1686830400.25).abs() < 1e-6);
+    }
+}
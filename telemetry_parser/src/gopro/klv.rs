@@ -162,7 +162,8 @@ impl KLV {
             b"WBAL" | b"ISOE" | b"SHUT" |
             b"MWET" | b"IORI" | b"CORI" |
             b"AALP" | b"WNDM" | b"UNIF" |
-            b"WRGB" | b"GPS5" | b"FASC" => TagId::Data,
+            b"WRGB" | b"GPS5" | b"GPS9" |
+            b"FASC" => TagId::Data,
 
             b"SIUN" | b"UNIT" => TagId::Unit,
             b"MTRX" => TagId::Matrix,
@@ -188,6 +189,7 @@ impl KLV {
             b"IORI" => GroupId::ImageOrientation,
             b"SHUT" => GroupId::Exposure,
             b"GPS5" => GroupId::GPS,
+            b"GPS9" => GroupId::Custom(super::gps::GPS9_GROUP.into()),
             b"FASC" => GroupId::Custom("FovAdaptationScore".into()),
             b"MWET" => GroupId::Custom("MicrophoneWet".into()),
             b"AALP" => GroupId::Custom("AGCAudioLevel".into()),
@@ -2,9 +2,10 @@
 // Copyright © 2021 Adrian <adrian.eddy at gmail>
 
 pub mod klv;
+pub mod gps;
 
 use std::io::*;
-use std::sync::{ Arc, atomic::AtomicBool };
+use std::sync::{ Arc, atomic::{ AtomicBool, Ordering::Relaxed } };
 use byteorder::{ ReadBytesExt, BigEndian };
 
 use crate::tags_impl::*;
@@ -12,6 +13,11 @@ use crate::*;
 use klv::KLV;
 use memchr::memmem;
 
+/// `DEVC` and `DVID` KLV headers
+const DEVC_HEADER_LEN: usize = 16;
+/// Read size of `GoPro::recover_samples`, larger than the largest `DEVC` (8 + 255 * 65535 bytes)
+const RECOVER_CHUNK_SIZE: usize = 32 * 1024 * 1024;
+
 #[derive(Default)]
 pub struct GoPro {
     pub model: Option<String>,
@@ -19,6 +25,7 @@ pub struct GoPro {
     frame_readout_time: Option<f64>,
     has_cori: bool,
     is_raw_gpmf: bool,
+    pub(crate) is_recovered: bool,
 }
 
 impl GoPro {
@@ -35,6 +42,10 @@ impl GoPro {
         self.frame_readout_time
     }
     pub fn normalize_imu_orientation(v: String) -> String {v}
+    /// The file has no `moov` and the GPMF payloads were found by scanning `mdat`
+    pub fn is_recovered(&self) -> bool {
+        self.is_recovered
+    }
 
     pub fn detect<P: AsRef<std::path::Path>>(buffer: &[u8], _filepath: P) -> Option<Self> {
         let mut ret = None;
@@ -92,6 +103,14 @@ impl GoPro {
                 }
             }
         }
+
+        // No index: the camera lost power while recording, but the payloads are still in `mdat`
+        if !ret.as_ref().is_some_and(|x| x.is_raw_gpmf) && memmem::find(buffer, b"moov").is_none() {
+            let has_devc = memmem::find_iter(buffer, b"DEVC").any(|pos| Self::devc_len(&buffer[pos..]).is_some());
+            if ret.is_some() || has_devc {
+                ret.get_or_insert_with(Self::default).is_recovered = true;
+            }
+        }
         ret
     }
 
@@ -117,8 +136,11 @@ impl GoPro {
                     }
                 }
             }
+        } else if self.is_recovered {
+            samples.extend(self.recover_samples(stream, size, &progress_cb, cancel_flag)?);
         } else {
-            let ctx = util::get_metadata_track_samples(stream, size, true, |mut info: SampleInfo, data: &[u8], file_position: u64, _video_md: Option<&VideoMetadata>| {
+            let extra_count = samples.len();
+            let res = util::get_metadata_track_samples(stream, size, true, |mut info: SampleInfo, data: &[u8], file_position: u64, _video_md: Option<&VideoMetadata>| {
                 if size > 0 {
                     progress_cb(file_position as f64 / size as f64);
                 }
@@ -129,12 +151,22 @@ impl GoPro {
                         samples.push(info);
                     }
                 }
-            }, cancel_flag)?;
-            if !ctx.tracks.is_empty() {
-                fps = util::get_fps_from_track(&ctx.tracks[0]);
+            }, cancel_flag.clone());
+            match res {
+                Ok(ctx) => {
+                    if !ctx.tracks.is_empty() {
+                        fps = util::get_fps_from_track(&ctx.tracks[0]);
+                    }
+                },
+                Err(e) => {
+                    log::warn!("{}, recovering the GPMF payloads from the file data", e);
+                    samples.truncate(extra_count);
+                    samples.extend(self.recover_samples(stream, size, &progress_cb, cancel_flag)?);
+                }
             }
         }
         self.process_samples(&mut samples, fps);
+        gps::process_gps(&mut samples);
 
         if self.model.as_ref().map(|x| x.contains("HERO5")).unwrap_or_default() {
             if samples.is_empty() {
@@ -184,6 +216,108 @@ impl GoPro {
         data.len() > 8 && &data[0..4] == b"DEVC"
     }
 
+    /// Size of the `DEVC` container at the start of `data` with its header, as declared in the header.
+    /// `None` if `data` doesn't start with a GPMF payload header
+    fn devc_len(data: &[u8]) -> Option<usize> {
+        if data.len() < DEVC_HEADER_LEN || &data[0..4] != b"DEVC" || data[4] != 0 || &data[8..12] != b"DVID" {
+            return None;
+        }
+        Some(8 + data[5] as usize * u16::from_be_bytes([data[6], data[7]]) as usize)
+    }
+
+    /// Scans the whole file for the GPMF payloads, for files without a usable `moov`.
+    /// The payload times come from `STMP`, or from the `TSMP` sample counts when there's no `STMP`
+    pub fn recover_samples<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, size: usize, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        self.recover_samples_in_chunks(stream, size, RECOVER_CHUNK_SIZE, progress_cb, cancel_flag)
+    }
+
+    /// The file is read `chunk_size` bytes at a time, a payload that continues past the chunk starts the next one
+    fn recover_samples_in_chunks<T: Read + Seek, F: Fn(f64)>(&mut self, stream: &mut T, size: usize, chunk_size: usize, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Vec<SampleInfo>> {
+        let mut samples = Vec::new();
+        let mut data = Vec::with_capacity(chunk_size.min(size));
+        let mut offset = 0;
+        'chunks: loop {
+            stream.seek(SeekFrom::Start(offset as u64))?;
+            data.clear();
+            stream.by_ref().take(chunk_size as u64).read_to_end(&mut data)?;
+            let is_last = data.len() < chunk_size;
+
+            let mut pos = 0;
+            let mut next = None;
+            while let Some(found) = memmem::find(&data[pos..], b"DEVC") {
+                if cancel_flag.load(Relaxed) { break 'chunks; }
+                let start = pos + found;
+                if !is_last && start + DEVC_HEADER_LEN > data.len() {
+                    next = Some(start);
+                    break;
+                }
+                match Self::devc_len(&data[start..]) {
+                    Some(len) if start + len <= data.len() => {
+                        if let Ok(mut map) = GoPro::parse_metadata(&data[start + 8..start + len], GroupId::Default, false) {
+                            self.process_map(&mut map);
+                            samples.push(SampleInfo { sample_index: samples.len() as u64, tag_map: Some(map), ..Default::default() });
+                        }
+                        pos = start + len;
+                        if size > 0 {
+                            progress_cb((offset + pos) as f64 / size as f64);
+                        }
+                    },
+                    Some(len) if !is_last && len <= chunk_size => {
+                        next = Some(start);
+                        break;
+                    },
+                    _ => pos = start + 4
+                }
+            }
+            if is_last { break; }
+            // a `DEVC` split by the chunk end is found in the next chunk
+            offset += next.unwrap_or_else(|| pos.max(data.len() - 3));
+        }
+        if samples.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "No GPMF payloads found"));
+        }
+        Self::recover_timing(&mut samples);
+        self.is_recovered = true;
+        Ok(samples)
+    }
+
+    /// Payload start in µs: `STMP` of the accelerometer stream (or any other one),
+    /// then the `TSMP` count of samples before the payload at `samples_per_s`
+    fn payload_time_us(info: &SampleInfo, samples_per_s: Option<f64>) -> Option<f64> {
+        let map = info.tag_map.as_ref()?;
+        let stmp = |m: &TagMap| (m.get_t(TagId::TimestampUs) as Option<&u64>).map(|x| *x as f64);
+        if let Some(t) = map.get(&GroupId::Accelerometer).and_then(stmp).or_else(|| map.values().find_map(stmp)) {
+            return Some(t);
+        }
+        let accl = map.get(&GroupId::Accelerometer)?;
+        let total = *(accl.get_t(TagId::Count) as Option<&u32>)? as f64;
+        let count = (accl.get_t(TagId::Data) as Option<&Vec<Vector3<i16>>>)?.len() as f64;
+        Some((total - count) / samples_per_s? * 1_000_000.0)
+    }
+
+    fn recover_timing(samples: &mut [SampleInfo]) {
+        // Without STMP the accelerometer rate is estimated from the first payload, which is about one second long
+        let samples_per_s = samples.iter().find_map(|info| {
+            let accl = info.tag_map.as_ref()?.get(&GroupId::Accelerometer)?;
+            (accl.get_t(TagId::Data) as Option<&Vec<Vector3<i16>>>).map(|v| v.len() as f64)
+        }).filter(|v| *v > 0.0);
+
+        let times = samples.iter().enumerate().map(|(i, info)| {
+            Self::payload_time_us(info, samples_per_s).unwrap_or(i as f64 * 1_000_000.0)
+        }).collect::<Vec<f64>>();
+        let first = times[0];
+
+        let mut prev_duration_ms = 1000.0;
+        for (i, info) in samples.iter_mut().enumerate() {
+            info.timestamp_ms = (times[i] - first) / 1000.0;
+            info.duration_ms = match times.get(i + 1) {
+                Some(next) if *next > times[i] => (next - times[i]) / 1000.0,
+                _ => prev_duration_ms
+            };
+            prev_duration_ms = info.duration_ms;
+        }
+    }
+
     pub fn parse_metadata(data: &[u8], group_id: GroupId, force_group: bool) -> Result<GroupedTagMap> {
         let mut slice = Cursor::new(data);
         let datalen = data.len() as u64;
@@ -356,6 +490,13 @@ impl GoPro {
             }
         }
     }
+    pub fn get_camera_serial(samples: &[SampleInfo]) -> Option<String> {
+        samples.iter()
+            .filter_map(|info| info.tag_map.as_ref())
+            .flat_map(|grouped_tag_map| grouped_tag_map.values())
+            .find_map(|map| map.values().find(|tag| tag.description == "CASN").map(|tag| tag.value.to_string()))
+    }
+
     pub fn get_avg_sample_duration(samples: &Vec<SampleInfo>, group_id: &GroupId) -> Option<f64> {
         let mut total_duration_ms = 0.0;
 
@@ -420,3 +561,90 @@ impl GoPro {
         }).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn klv(key: &[u8; 4], typ: u8, size: u8, data: &[u8]) -> Vec<u8> {
+        let mut out = key.to_vec();
+        out.push(typ);
+        out.push(size);
+        out.extend(((data.len() / size as usize) as u16).to_be_bytes());
+        out.extend(data);
+        out.resize(out.len() + (4 - data.len() % 4) % 4, 0);
+        out
+    }
+
+    /// GPMF payload with `count` accelerometer samples, `tsmp` of them since the start of the recording
+    fn devc(stmp: Option<u64>, tsmp: u32, count: usize) -> Vec<u8> {
+        let mut strm = Vec::new();
+        if let Some(stmp) = stmp {
+            strm.extend(klv(b"STMP", b'J', 8, &stmp.to_be_bytes()));
+        }
+        strm.extend(klv(b"TSMP", b'L', 4, &tsmp.to_be_bytes()));
+        strm.extend(klv(b"SCAL", b's', 2, &418i16.to_be_bytes()));
+        let accl = (0..count).flat_map(|i| [418i16, 0, i as i16]).flat_map(i16::to_be_bytes).collect::<Vec<u8>>();
+        strm.extend(klv(b"ACCL", b's', 6, &accl));
+
+        let mut inner = klv(b"DVID", b'L', 4, &1u32.to_be_bytes());
+        inner.extend(klv(b"STRM", 0, 1, &strm));
+        klv(b"DEVC", 0, 1, &inner)
+    }
+
+    fn payload_samples(payloads: &[Vec<u8>]) -> Vec<SampleInfo> {
+        payloads.iter().map(|data| {
+            let len = GoPro::devc_len(data).unwrap();
+            let map = GoPro::parse_metadata(&data[8..len], GroupId::Default, false).unwrap();
+            SampleInfo { tag_map: Some(map), ..Default::default() }
+        }).collect()
+    }
+
+    #[test]
+    fn devc_len_reads_the_payload_header() {
+        let payload = devc(Some(1_000_000), 20, 20);
+        assert_eq!(GoPro::devc_len(&payload), Some(payload.len()));
+        // the declared size, even if the data is cut
+        assert_eq!(GoPro::devc_len(&payload[..40]), Some(payload.len()));
+
+        assert_eq!(GoPro::devc_len(&payload[..12]), None);
+        assert_eq!(GoPro::devc_len(b"DEVC\0\x01\x00\x10STRM\0\0\0\0"), None);
+        assert_eq!(GoPro::devc_len(b"DEVCL\x04\x00\x01DVID\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn recover_timing_from_stmp() {
+        let mut samples = payload_samples(&[devc(Some(5_000_000), 200, 200), devc(Some(6_001_000), 400, 200)]);
+        GoPro::recover_timing(&mut samples);
+        assert_eq!(samples.iter().map(|s| s.timestamp_ms).collect::<Vec<_>>(), [0.0, 1001.0]);
+        assert_eq!(samples.iter().map(|s| s.duration_ms).collect::<Vec<_>>(), [1001.0, 1001.0]);
+    }
+
+    #[test]
+    fn recover_timing_from_tsmp_without_stmp() {
+        // 200 samples per payload, so 200 samples per second
+        let mut samples = payload_samples(&[devc(None, 200, 200), devc(None, 400, 200), devc(None, 500, 100)]);
+        GoPro::recover_timing(&mut samples);
+        // the last payload is shorter, it starts after 400 samples
+        assert_eq!(samples.iter().map(|s| s.timestamp_ms).collect::<Vec<_>>(), [0.0, 1000.0, 2000.0]);
+        assert_eq!(samples.iter().map(|s| s.duration_ms).collect::<Vec<_>>(), [1000.0, 1000.0, 1000.0]);
+    }
+
+    #[test]
+    fn recover_samples_across_chunks() {
+        let mut file = b"\0\0\0\x08mdat".to_vec();
+        for i in 0..5u64 {
+            file.resize(file.len() + 37 * i as usize + 5, 0xab);
+            file.extend(devc(Some(5_000_000 + i * 1_001_000), 20 * (i as u32 + 1), 20));
+        }
+        // truncated payload at the end of the file
+        file.extend(&devc(Some(10_005_000), 120, 20)[..60]);
+
+        for chunk_size in [300, 333, 512, 1 << 20] {
+            let mut gopro = GoPro::default();
+            let samples = gopro.recover_samples_in_chunks(&mut Cursor::new(&file), file.len(), chunk_size, |_| (), Arc::new(AtomicBool::new(false))).unwrap();
+            assert_eq!(samples.iter().map(|s| s.timestamp_ms).collect::<Vec<_>>(), [0.0, 1001.0, 2002.0, 3003.0, 4004.0], "chunk size {}", chunk_size);
+            assert!(gopro.is_recovered());
+        }
+    }
+}
@@ -7,6 +7,12 @@ mod gyroflow;
 mod insta360;
 mod dji;
 mod freefly;
+mod flysight;
+mod garmin;
+mod blackbox;
+mod camm;
+mod ardupilot;
+pub mod generic_csv;
 
 pub mod tags_impl;
 pub mod util;
@@ -16,6 +22,9 @@ use std::io::*;
 use std::sync::{ Arc, atomic::AtomicBool };
 use util::*;
 
+/// Changes with every release, caches of parsed data are invalidated by it
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 macro_rules! impl_formats {
     ($($name:ident => $class:ty,)*) => {
         pub enum SupportedFormats {
@@ -27,6 +36,10 @@ macro_rules! impl_formats {
         }
         impl Input {
             pub fn from_stream<T: Read + Seek, P: AsRef<std::path::Path>, F: Fn(f64)>(stream: &mut T, size: usize, filepath: P, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Input> {
+                Self::from_stream_with_csv_mapping(stream, size, filepath, None, progress_cb, cancel_flag)
+            }
+            /// Like `from_stream`, `csv_mapping` is used for the CSV files without a sidecar mapping (`generic_csv::GenericCsv::sidecar_paths`)
+            pub fn from_stream_with_csv_mapping<T: Read + Seek, P: AsRef<std::path::Path>, F: Fn(f64)>(stream: &mut T, size: usize, filepath: P, csv_mapping: Option<&generic_csv::CsvMapping>, progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Input> {
                 let read_mb = if size as u64 > 30u64*1024*1024*1024 { // If file is greater than 30 GB, read 30 MB header/footer
                     30
                 } else if size as u64 > 5u64*1024*1024*1024 { // If file is greater than 5 GB, read 10 MB header/footer
@@ -56,6 +69,18 @@ macro_rules! impl_formats {
                         }
                     }
                 )*
+                if let Some(mapping) = csv_mapping {
+                    let path = filepath.as_ref().to_str().unwrap_or_default();
+                    let is_csv = ext.as_ref().map_or(true, |ext| generic_csv::GenericCsv::possible_extensions().contains(&ext.as_str()));
+                    if is_csv && generic_csv::GenericCsv::sidecar_paths(path).is_empty() {
+                        if let Some(mut x) = generic_csv::GenericCsv::with_mapping(&buf, mapping.clone()) {
+                            return Ok(Input {
+                                samples: x.parse(stream, size, progress_cb, cancel_flag).ok(),
+                                inner: SupportedFormats::GenericCsv(x)
+                            });
+                        }
+                    }
+                }
                 return Err(Error::new(ErrorKind::Other, "Unsupported file format"));
             }
             pub fn camera_type(&self) -> String {
@@ -88,10 +113,103 @@ macro_rules! impl_formats {
 }
 
 impl_formats! {
-    GoPro     => gopro::GoPro,
-    Sony      => sony::Sony,
-    Dji       => dji::Dji,
-    Insta360  => insta360::Insta360,
-    Gyroflow  => gyroflow::Gyroflow,
-    Freefly   => freefly::Freefly,
+    GoPro      => gopro::GoPro,
+    Sony       => sony::Sony,
+    Dji        => dji::Dji,
+    Insta360   => insta360::Insta360,
+    Gyroflow   => gyroflow::Gyroflow,
+    Freefly    => freefly::Freefly,
+    FlySight   => flysight::FlySight,
+    Garmin     => garmin::Garmin,
+    Blackbox   => blackbox::Blackbox,
+    Camm       => camm::Camm,
+    ArduPilot  => ardupilot::ArduPilot,
+    GenericCsv => generic_csv::GenericCsv,
+}
+
+impl Input {
+    /// Opens the chapters of one recording (`filesystem::gopro_chapter_paths`) as a single `Input`.
+    /// Sample timestamps of every chapter continue from the end of the previous one,
+    /// chapters from a camera with a different serial or that can't be read are skipped
+    pub fn from_chapters<F: Fn(f64)>(paths: &[String], progress_cb: F, cancel_flag: Arc<AtomicBool>) -> Result<Input> {
+        let base = filesystem::get_base();
+        let mut joined: Option<Input> = None;
+        let mut serial = None;
+        let mut last_error = None;
+        for (i, path) in paths.iter().enumerate() {
+            // a truncated chapter without `moov` is recovered by the GoPro parser, anything else unreadable is skipped
+            let chapter = filesystem::open_file(&base, path).and_then(|mut wrapper| {
+                Input::from_stream(&mut wrapper.file, wrapper.size, path, |p| progress_cb((i as f64 + p) / paths.len() as f64), cancel_flag.clone())
+            });
+            let chapter = match chapter {
+                Ok(chapter) => chapter,
+                Err(e) => {
+                    log::warn!("Chapter {} skipped: {}", path, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            let chapter_serial = chapter.samples.as_ref().and_then(|s| gopro::GoPro::get_camera_serial(s));
+
+            match joined {
+                None => {
+                    serial = chapter_serial;
+                    joined = Some(chapter);
+                },
+                Some(ref mut input) => {
+                    if chapter_serial != serial {
+                        log::warn!("Chapter {} is from another camera ({:?} != {:?}), skipped", path, chapter_serial, serial);
+                        continue;
+                    }
+                    input.append_chapter(chapter);
+                }
+            }
+        }
+        joined.ok_or_else(|| last_error.unwrap_or_else(|| Error::other("No chapters to open")))
+    }
+
+    /// Files next to `path` that are read along with it (FlySight 2 siblings, CSV mappings)
+    pub fn sidecar_paths(path: &str) -> Vec<String> {
+        let mut paths = flysight::FlySight::sidecar_paths(path);
+        paths.extend(generic_csv::GenericCsv::sidecar_paths(path));
+        paths
+    }
+
+    /// GoPro file without `moov`, the samples were recovered from the GPMF payloads in `mdat`
+    pub fn is_recovered(&self) -> bool {
+        matches!(&self.inner, SupportedFormats::GoPro(x) if x.is_recovered())
+    }
+
+    fn append_chapter(&mut self, chapter: Input) {
+        use tags_impl::GetWithType;
+
+        // a truncated last chapter makes the whole recording recovered
+        if let (SupportedFormats::GoPro(x), true) = (&mut self.inner, chapter.is_recovered()) {
+            x.is_recovered = true;
+        }
+
+        let mut chapter_samples = match chapter.samples {
+            Some(samples) if !samples.is_empty() => samples,
+            _ => return
+        };
+        let samples = self.samples.get_or_insert_with(Vec::new);
+
+        let stmp = |info: &SampleInfo| info.tag_map.as_ref().and_then(|map| {
+            map.values().find_map(|m| m.get_t(tags_impl::TagId::TimestampUs) as Option<&u64>).map(|x| *x as i64)
+        });
+        let index_offset = samples.iter().map(|x| x.sample_index + 1).max().unwrap_or(0);
+        let t_offset_ms  = samples.last().map(|x| x.timestamp_ms + x.duration_ms).unwrap_or(0.0);
+
+        // STMP normally continues over the chapters, if it starts again it's moved after the previous chapter
+        let first_stmp      = samples.iter().find_map(stmp);
+        let last_stmp       = samples.iter().rev().find_map(stmp);
+        let chapter_stmp    = chapter_samples.iter().find_map(stmp);
+        let stmp_offset_us  = match (first_stmp, last_stmp, chapter_stmp) {
+            (Some(first), Some(last), Some(chapter)) if chapter <= last => first + (t_offset_ms * 1000.0).round() as i64 - chapter,
+            _ => 0
+        };
+
+        shift_sample_timestamps(&mut chapter_samples, index_offset, t_offset_ms, stmp_offset_us);
+        samples.extend(chapter_samples);
+    }
 }
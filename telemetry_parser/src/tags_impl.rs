@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // Copyright © 2021 Adrian <adrian.eddy at gmail>
 
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use std::collections::*;
 
 macro_rules! declare_groups {
@@ -122,6 +122,15 @@ macro_rules! declare_types {
                 }
             }
         }
+        impl TagValue {
+            /// Bytes the value was parsed from, empty for values created already parsed without them
+            pub fn raw_data(&self) -> &[u8] {
+                match &self {
+                    $(TagValue::$field(t) => &t.raw_data,)*
+                    TagValue::Unknown(t) => &t.raw_data,
+                }
+            }
+        }
 
         /*impl<T> std::convert::TryInto<ValueType<T>> for TagValue {
             type Error = &'static str;
@@ -179,6 +188,18 @@ pub enum Scalar {
     String(String),
     bool(bool),
 }
+impl Scalar {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Scalar::u8(v)  => Some(*v as f64), Scalar::i8(v)  => Some(*v as f64),
+            Scalar::u16(v) => Some(*v as f64), Scalar::i16(v) => Some(*v as f64),
+            Scalar::u32(v) => Some(*v as f64), Scalar::i32(v) => Some(*v as f64),
+            Scalar::u64(v) => Some(*v as f64), Scalar::i64(v) => Some(*v as f64),
+            Scalar::f32(v) => Some(*v as f64), Scalar::f64(v) => Some(*v),
+            Scalar::String(_) | Scalar::bool(_) => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct TagDescription {
@@ -298,7 +319,7 @@ pub struct TimeScalar<T> {
     pub t: f64,
     pub v: T
 }
-#[derive(Debug, Copy, Clone, Serialize, Default)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Default)]
 pub struct Quaternion<T> {
     pub w: T,
     pub x: T,
@@ -348,7 +369,7 @@ pub struct TimeQuaternion<T> {
     pub v: Quaternion<T>
 }
 
-#[derive(Debug, Clone, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct GpsData {
     pub is_acquired: bool,
     pub unix_timestamp: f64,
@@ -619,11 +619,69 @@ pub fn find_from_to(buffer: &[u8], from: &[u8], to: &[u8]) -> Option<String> {
     Some(String::from_utf8_lossy(&buffer[pos..pos+from.len()+end+to.len()]).into())
 }
 
+/// Moves `samples` of a chapter to the end of the previous one: sample index, sample time,
+/// STMP tags (by `stmp_offset_us`) and quaternion times (by `t_offset_ms`)
+pub fn shift_sample_timestamps(samples: &mut [SampleInfo], index_offset: u64, t_offset_ms: f64, stmp_offset_us: i64) {
+    for info in samples.iter_mut() {
+        info.sample_index += index_offset;
+        info.timestamp_ms += t_offset_ms;
+        let grouped_tag_map = match info.tag_map.as_mut() {
+            Some(map) => map,
+            None => continue
+        };
+        for (group, map) in grouped_tag_map.iter_mut() {
+            if stmp_offset_us != 0 {
+                if let Some(tag) = map.get_mut(&TagId::TimestampUs) {
+                    if let TagValue::u64(v) = &tag.value {
+                        let shifted = (*v.get() as i64 + stmp_offset_us).max(0) as u64;
+                        tag.value = TagValue::u64(ValueType::new_parsed(|v| format!("{}", v), shifted, v.raw_data.clone()));
+                    }
+                }
+            }
+            if group == &GroupId::Quaternion && t_offset_ms != 0.0 {
+                if let Some(tag) = map.get_mut(&TagId::Data) {
+                    if let TagValue::Vec_TimeQuaternion_f64(v) = &tag.value {
+                        let shifted: Vec<TimeQuaternion<f64>> = v.get().iter().map(|q| TimeQuaternion { t: q.t + t_offset_ms, v: q.v }).collect();
+                        tag.value = TagValue::Vec_TimeQuaternion_f64(ValueType::new_parsed(|v| format!("{:?}", v), shifted, v.raw_data.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub fn insert_tag(map: &mut GroupedTagMap, tag: TagDescription) {
     let group_map = map.entry(tag.group.clone()).or_insert_with(TagMap::new);
     group_map.insert(tag.id.clone(), tag);
 }
 
+/// `GroupId::Custom` name of the pressure data (`Vec_TimeScalar_f64`, Pa) shared by the formats with a barometer
+pub const BARO_GROUP: &str = "Barometer";
+
+/// GPS epoch 1980-01-06 in unix time
+pub const GPS_EPOCH: f64 = 315964800.0;
+/// GPS - UTC, since 2017
+pub const GPS_LEAP_SECONDS: f64 = 18.0;
+
+/// Initial bearing from `a` to `b`, degrees 0..360
+fn bearing(a: &GpsData, b: &GpsData) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlon = (b.lon - a.lon).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Fills `track` for sources that don't record the course, it's the bearing from the previous acquired position
+pub(crate) fn fill_track(gps: &mut [GpsData], prev: &mut Option<GpsData>) {
+    for point in gps.iter_mut().filter(|p| p.is_acquired) {
+        if let Some(p) = prev.as_ref() {
+            point.track = if p.lat != point.lat || p.lon != point.lon { bearing(p, point) } else { p.track };
+        }
+        *prev = Some(point.clone());
+    }
+}
+
 pub fn create_csv_map<'a, 'b>(row: &'b csv::StringRecord, headers: &'a Vec<String>) -> BTreeMap<&'a str, &'b str> {
     headers.iter().zip(row).map(|(a, b)| (&a[..], b.trim())).collect()
 }